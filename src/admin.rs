@@ -0,0 +1,241 @@
+use crate::model::{RequestId, SkipReason};
+use alloy::primitives::B256;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// Operator controls over which fills the solver may make, set through the admin API.
+// They only stop new fills: auctions keep being tracked and priced, and trades that
+// are already executing are left to finish.
+#[derive(Default)]
+pub(crate) struct AdminControls {
+    paused: AtomicBool,
+    draining: AtomicBool,
+    executing: AtomicUsize, // trades handed to the executor that haven't finished yet
+    paused_chains: RwLock<BTreeSet<u64>>,
+    skipped_requests: RwLock<BTreeSet<RequestId>>,
+    tripped_chains: RwLock<BTreeMap<u64, String>>, // halted by the circuit breaker, with why, until reset
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ControlsReport {
+    pub paused: bool,
+    pub draining: bool,
+    pub drained: bool, // draining and nothing left executing, i.e. safe to stop
+    pub executing: usize,
+    pub paused_chains: Vec<u64>,
+    pub skipped_requests: Vec<B256>,
+    pub tripped_chains: BTreeMap<u64, String>,
+}
+
+impl AdminControls {
+    // Whether a new fill of `request_id` on `dest_chain_id` is allowed
+    pub fn check(&self, dest_chain_id: u64, request_id: &RequestId) -> Result<(), SkipReason> {
+        if self.draining.load(Ordering::SeqCst) {
+            return Err(SkipReason::Draining);
+        }
+        if self.paused.load(Ordering::SeqCst) {
+            return Err(SkipReason::Paused);
+        }
+        if self.paused_chains.read().expect("controls lock poisoned").contains(&dest_chain_id) {
+            return Err(SkipReason::ChainPaused);
+        }
+        if self.skipped_requests.read().expect("controls lock poisoned").contains(request_id) {
+            return Err(SkipReason::RequestSkipped);
+        }
+        Ok(())
+    }
+
+    // Whether a fill from `src_chain_id` to `dest_chain_id` is allowed by the circuit breaker
+    pub fn check_circuit(&self, src_chain_id: u64, dest_chain_id: u64) -> Result<(), SkipReason> {
+        let tripped = self.tripped_chains.read().expect("controls lock poisoned");
+        if tripped.contains_key(&src_chain_id) || tripped.contains_key(&dest_chain_id) {
+            return Err(SkipReason::CircuitOpen);
+        }
+        Ok(())
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    // resuming also cancels a drain
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.draining.store(false, Ordering::SeqCst);
+    }
+
+    pub fn drain(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn pause_chain(&self, chain_id: u64) {
+        self.paused_chains.write().expect("controls lock poisoned").insert(chain_id);
+    }
+
+    pub fn resume_chain(&self, chain_id: u64) {
+        self.paused_chains.write().expect("controls lock poisoned").remove(&chain_id);
+    }
+
+    pub fn skip_request(&self, request_id: RequestId) {
+        self.skipped_requests.write().expect("controls lock poisoned").insert(request_id);
+    }
+
+    pub fn unskip_request(&self, request_id: &RequestId) {
+        self.skipped_requests.write().expect("controls lock poisoned").remove(request_id);
+    }
+
+    // Keeps the first reason a chain was tripped for
+    pub fn trip(&self, chain_id: u64, reason: String) {
+        self.tripped_chains.write().expect("controls lock poisoned").entry(chain_id).or_insert(reason);
+    }
+
+    pub fn reset_circuit(&self, chain_id: u64) {
+        self.tripped_chains.write().expect("controls lock poisoned").remove(&chain_id);
+    }
+
+    pub fn is_tripped(&self, chain_id: u64) -> bool {
+        self.tripped_chains.read().expect("controls lock poisoned").contains_key(&chain_id)
+    }
+
+    pub fn executing_started(&self, trades: usize) {
+        self.executing.fetch_add(trades, Ordering::SeqCst);
+    }
+
+    pub fn executing_finished(&self, trades: usize) {
+        self.executing.fetch_sub(trades, Ordering::SeqCst);
+    }
+
+    pub fn report(&self) -> ControlsReport {
+        let draining = self.draining.load(Ordering::SeqCst);
+        let executing = self.executing.load(Ordering::SeqCst);
+        ControlsReport {
+            paused: self.paused.load(Ordering::SeqCst),
+            draining,
+            drained: draining && executing == 0,
+            executing,
+            paused_chains: self.paused_chains.read().expect("controls lock poisoned").iter().copied().collect(),
+            skipped_requests: self
+                .skipped_requests
+                .read()
+                .expect("controls lock poisoned")
+                .iter()
+                .map(|id| B256::from(*id))
+                .collect(),
+            tripped_chains: self.tripped_chains.read().expect("controls lock poisoned").clone(),
+        }
+    }
+}
+
+// Asks the solver running on this machine to reset the circuit breaker of a chain, for `circuit reset`
+pub(crate) async fn reset_circuit_remotely(port: u16, admin_token: Option<&str>, chain_id: u64) -> eyre::Result<()> {
+    let token = admin_token.ok_or_else(|| eyre::eyre!("an admin token is needed to reset the circuit breaker"))?;
+    let response = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/admin/chains/{}/reset", port, chain_id))
+        .bearer_auth(token)
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        eyre::bail!("resetting the circuit breaker of chain {} failed with {}: {}", chain_id, status, response.text().await.unwrap_or_default());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::AdminControls;
+    use crate::model::SkipReason;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn fills_are_allowed_by_default() {
+        let controls = AdminControls::default();
+        assert_that!(controls.check(1, &[1; 32])).is_ok();
+    }
+
+    #[test]
+    fn pausing_blocks_every_fill_until_resumed() {
+        // given
+        let controls = AdminControls::default();
+
+        // when
+        controls.pause();
+        let paused = controls.check(1, &[1; 32]);
+        controls.resume();
+        let resumed = controls.check(1, &[1; 32]);
+
+        // then
+        assert_that!(paused).is_err_containing(SkipReason::Paused);
+        assert_that!(resumed).is_ok();
+    }
+
+    #[test]
+    fn pausing_a_chain_only_blocks_fills_on_that_chain() {
+        // given
+        let controls = AdminControls::default();
+
+        // when
+        controls.pause_chain(2);
+
+        // then
+        assert_that!(controls.check(2, &[1; 32])).is_err_containing(SkipReason::ChainPaused);
+        assert_that!(controls.check(1, &[1; 32])).is_ok();
+        controls.resume_chain(2);
+        assert_that!(controls.check(2, &[1; 32])).is_ok();
+    }
+
+    #[test]
+    fn skipped_requests_are_never_filled() {
+        // given
+        let controls = AdminControls::default();
+
+        // when
+        controls.skip_request([1; 32]);
+
+        // then
+        assert_that!(controls.check(1, &[1; 32])).is_err_containing(SkipReason::RequestSkipped);
+        assert_that!(controls.check(1, &[2; 32])).is_ok();
+        controls.unskip_request(&[1; 32]);
+        assert_that!(controls.check(1, &[1; 32])).is_ok();
+    }
+
+    #[test]
+    fn tripped_chains_block_fills_to_and_from_them_until_reset() {
+        // given
+        let controls = AdminControls::default();
+
+        // when
+        controls.trip(2, "3 fills in a row reverted".to_string());
+        controls.trip(2, "refund overdue".to_string());
+
+        // then
+        assert_that!(controls.check_circuit(2, 1)).is_err_containing(SkipReason::CircuitOpen);
+        assert_that!(controls.check_circuit(1, 2)).is_err_containing(SkipReason::CircuitOpen);
+        assert_that!(controls.check_circuit(1, 3)).is_ok();
+        assert_that!(controls.report().tripped_chains[&2].as_str()).is_equal_to("3 fills in a row reverted");
+        controls.reset_circuit(2);
+        assert_that!(controls.check_circuit(2, 1)).is_ok();
+    }
+
+    #[test]
+    fn draining_is_done_once_executing_trades_finish() {
+        // given
+        let controls = AdminControls::default();
+        controls.executing_started(2);
+
+        // when
+        controls.drain();
+        let while_executing = controls.report();
+        controls.executing_finished(2);
+        let after = controls.report();
+
+        // then
+        assert_that!(controls.check(1, &[1; 32])).is_err_containing(SkipReason::Draining);
+        assert_that!(while_executing.drained).is_false();
+        assert_that!(while_executing.executing).is_equal_to(2);
+        assert_that!(after.drained).is_true();
+    }
+}
@@ -1,19 +1,75 @@
-use axum::Router;
-use axum::routing::get;
+use crate::admin::AdminControls;
+use crate::events::SolverEvent;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::model::RequestId;
+use crate::status::SolverStatus;
+use alloy::primitives::{B256, keccak256};
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use axum::middleware::{self, Next};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::Stream;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::net::TcpListener;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::info;
 
 pub(crate) struct ApiServer {
     port: u16,
     app: Router<()>,
 }
+
+#[derive(Clone)]
+struct ApiState {
+    metrics: Arc<Metrics>,
+    health: Arc<Health>,
+    status: Arc<SolverStatus>,
+    controls: Arc<AdminControls>,
+}
+
 impl ApiServer {
-    pub fn new(port: u16) -> Self {
-        let app = Router::new().route("/health", get(healthcheck_handler));
-        Self { port, app }
+    pub fn new(
+        port: u16,
+        admin_token: Option<String>,
+        metrics: Arc<Metrics>,
+        health: Arc<Health>,
+        status: Arc<SolverStatus>,
+        controls: Arc<AdminControls>,
+    ) -> eyre::Result<Self> {
+        let mut app = Router::new()
+            .route("/health", get(healthcheck_handler))
+            .route("/livez", get(healthcheck_handler))
+            .route("/readyz", get(readiness_handler))
+            .route("/metrics", get(metrics_handler))
+            .route("/chains", get(chains_handler))
+            .route("/chains/{chain_id}/state", get(chain_state_handler))
+            .route("/auctions", get(auctions_handler))
+            .route("/auctions/{request_id}", get(auction_handler))
+            .route("/trades", get(trades_handler))
+            .route("/skips", get(skips_handler))
+            .route("/events", get(events_handler));
+
+        // the admin API can stop the solver trading, so we only serve it if a token has been configured, and
+        // refuse to start with one anybody could guess
+        match admin_token {
+            Some(token) if token.trim().is_empty() => eyre::bail!("the admin token must not be empty"),
+            Some(token) => app = app.merge(admin_routes(token)),
+            None => info!("no admin token configured, admin API disabled"),
+        }
+
+        let app = app.with_state(ApiState { metrics, health, status, controls });
+        Ok(Self { port, app })
     }
 
     pub async fn start(self) -> eyre::Result<()> {
-        println!("Listening on port {}", self.port);
+        info!(port = self.port, "listening");
         let listener = TcpListener::bind(("0.0.0.0", self.port)).await?;
         Ok(axum::serve(listener, self.app).await?)
     }
@@ -22,3 +78,237 @@ impl ApiServer {
 async fn healthcheck_handler() -> &'static str {
     "ok"
 }
+
+async fn readiness_handler(State(state): State<ApiState>) -> Response {
+    let report = state.health.report(Instant::now());
+    let status = if report.ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    (status, Json(report)).into_response()
+}
+
+async fn metrics_handler(State(state): State<ApiState>) -> Response {
+    match state.metrics.render() {
+        Ok(body) => ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("error rendering metrics: {e}")).into_response(),
+    }
+}
+
+async fn chains_handler(State(state): State<ApiState>) -> Response {
+    Json(state.status.chains()).into_response()
+}
+
+async fn chain_state_handler(State(state): State<ApiState>, Path(chain_id): Path<u64>) -> Response {
+    match state.status.chain(chain_id) {
+        Some(chain) => Json(chain).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("chain {chain_id} is not configured")).into_response(),
+    }
+}
+
+async fn auctions_handler(State(state): State<ApiState>) -> Response {
+    Json(state.status.auctions()).into_response()
+}
+
+async fn auction_handler(State(state): State<ApiState>, Path(request_id): Path<String>) -> Response {
+    let id = match parse_request_id(&request_id) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+    match state.status.auction(&id) {
+        Some(auction) => Json(auction).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("no active auction for request {request_id}")).into_response(),
+    }
+}
+
+async fn trades_handler(State(state): State<ApiState>) -> Response {
+    Json(state.status.trades()).into_response()
+}
+
+async fn skips_handler(State(state): State<ApiState>) -> Response {
+    Json(state.status.skips()).into_response()
+}
+
+// Server-sent events of every `SolverEvent` from the moment the client connects
+async fn events_handler(State(state): State<ApiState>) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let stream = futures::stream::unfold(state.status.subscribe(), |mut events: Receiver<SolverEvent>| async move {
+        let event = match events.recv().await {
+            Ok(event) => Event::default().json_data(&event),
+            // let slow clients know they've missed some rather than silently skipping them
+            Err(RecvError::Lagged(missed)) => Ok(Event::default().event("lagged").data(missed.to_string())),
+            Err(RecvError::Closed) => return None,
+        };
+        Some((event, events))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn admin_routes(token: String) -> Router<ApiState> {
+    Router::new()
+        .route("/admin/status", get(admin_status_handler))
+        .route("/admin/pause", post(pause_handler))
+        .route("/admin/resume", post(resume_handler))
+        .route("/admin/drain", post(drain_handler))
+        .route("/admin/chains/{chain_id}/pause", post(pause_chain_handler))
+        .route("/admin/chains/{chain_id}/resume", post(resume_chain_handler))
+        .route("/admin/chains/{chain_id}/reset", post(reset_circuit_handler))
+        .route("/admin/requests/{request_id}/skip", post(skip_request_handler))
+        .route("/admin/requests/{request_id}/unskip", post(unskip_request_handler))
+        .route_layer(middleware::from_fn_with_state(Arc::new(token), require_admin_token))
+}
+
+async fn require_admin_token(State(token): State<Arc<String>>, request: Request, next: Next) -> Response {
+    let authorised = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+    if !authorised {
+        return (StatusCode::UNAUTHORIZED, "missing or invalid admin token").into_response();
+    }
+    next.run(request).await
+}
+
+// Compares digests of the two, so that response times leak neither how much of the token a caller got right
+// nor how long it is
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let (a, b) = (keccak256(a), keccak256(b));
+    a.iter().zip(b.iter()).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn admin_status_handler(State(state): State<ApiState>) -> Response {
+    Json(state.controls.report()).into_response()
+}
+
+async fn pause_handler(State(state): State<ApiState>) -> Response {
+    info!("admin paused all fills");
+    state.controls.pause();
+    Json(state.controls.report()).into_response()
+}
+
+async fn resume_handler(State(state): State<ApiState>) -> Response {
+    info!("admin resumed fills");
+    state.controls.resume();
+    Json(state.controls.report()).into_response()
+}
+
+async fn drain_handler(State(state): State<ApiState>) -> Response {
+    info!("admin started draining");
+    state.controls.drain();
+    Json(state.controls.report()).into_response()
+}
+
+async fn pause_chain_handler(State(state): State<ApiState>, Path(chain_id): Path<u64>) -> Response {
+    info!(chain_id, "admin paused fills on chain");
+    state.controls.pause_chain(chain_id);
+    Json(state.controls.report()).into_response()
+}
+
+async fn resume_chain_handler(State(state): State<ApiState>, Path(chain_id): Path<u64>) -> Response {
+    info!(chain_id, "admin resumed fills on chain");
+    state.controls.resume_chain(chain_id);
+    Json(state.controls.report()).into_response()
+}
+
+async fn reset_circuit_handler(State(state): State<ApiState>, Path(chain_id): Path<u64>) -> Response {
+    info!(chain_id, "admin reset circuit breaker for chain");
+    state.controls.reset_circuit(chain_id);
+    Json(state.controls.report()).into_response()
+}
+
+async fn skip_request_handler(State(state): State<ApiState>, Path(request_id): Path<String>) -> Response {
+    let id = match parse_request_id(&request_id) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+    info!(%request_id, "admin force-skipped request");
+    state.controls.skip_request(id);
+    Json(state.controls.report()).into_response()
+}
+
+async fn unskip_request_handler(State(state): State<ApiState>, Path(request_id): Path<String>) -> Response {
+    let id = match parse_request_id(&request_id) {
+        Ok(id) => id,
+        Err(e) => return e.into_response(),
+    };
+    info!(%request_id, "admin stopped skipping request");
+    state.controls.unskip_request(&id);
+    Json(state.controls.report()).into_response()
+}
+
+fn parse_request_id(request_id: &str) -> Result<RequestId, (StatusCode, String)> {
+    request_id
+        .parse::<B256>()
+        .map(RequestId::from)
+        .map_err(|_| (StatusCode::BAD_REQUEST, format!("{request_id} is not a valid request id")))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::AdminControls;
+    use crate::api::ApiServer;
+    use crate::health::Health;
+    use crate::metrics::Metrics;
+    use crate::status::SolverStatus;
+    use axum::body::Body;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::{Method, Request, StatusCode};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn admin_routes_need_the_admin_token() {
+        // given
+        let server = server(Some("secret")).unwrap();
+
+        // when
+        let without_token = status_of(&server, Method::GET, "/admin/status", None).await;
+        let wrong_token = status_of(&server, Method::POST, "/admin/pause", Some("Bearer guess")).await;
+        let prefix_of_token = status_of(&server, Method::GET, "/admin/status", Some("Bearer secre")).await;
+        let not_bearer = status_of(&server, Method::GET, "/admin/status", Some("secret")).await;
+        let right_token = status_of(&server, Method::GET, "/admin/status", Some("Bearer secret")).await;
+        let introspection = status_of(&server, Method::GET, "/trades", None).await;
+
+        // then
+        assert_that!(without_token).is_equal_to(StatusCode::UNAUTHORIZED);
+        assert_that!(wrong_token).is_equal_to(StatusCode::UNAUTHORIZED);
+        assert_that!(prefix_of_token).is_equal_to(StatusCode::UNAUTHORIZED);
+        assert_that!(not_bearer).is_equal_to(StatusCode::UNAUTHORIZED);
+        assert_that!(right_token).is_equal_to(StatusCode::OK);
+        assert_that!(introspection).is_equal_to(StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn admin_routes_are_not_served_without_a_token() {
+        let server = server(None).unwrap();
+        assert_that!(status_of(&server, Method::GET, "/admin/status", Some("Bearer secret")).await).is_equal_to(StatusCode::NOT_FOUND);
+        assert_that!(status_of(&server, Method::POST, "/admin/pause", None).await).is_equal_to(StatusCode::NOT_FOUND);
+        assert_that!(status_of(&server, Method::GET, "/auctions", None).await).is_equal_to(StatusCode::OK);
+    }
+
+    #[test]
+    fn blank_admin_tokens_are_refused() {
+        assert_that!(server(Some("")).err().map(|e| e.to_string())).contains("the admin token must not be empty".to_string());
+        assert_that!(server(Some(" \t")).err().map(|e| e.to_string())).contains("the admin token must not be empty".to_string());
+    }
+
+    fn server(admin_token: Option<&str>) -> eyre::Result<ApiServer> {
+        ApiServer::new(
+            0,
+            admin_token.map(str::to_string),
+            Arc::new(Metrics::new()),
+            Arc::new(Health::new(Duration::from_secs(60), &[])),
+            Arc::new(SolverStatus::default()),
+            Arc::new(AdminControls::default()),
+        )
+    }
+
+    async fn status_of(server: &ApiServer, method: Method, uri: &str, authorization: Option<&str>) -> StatusCode {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(authorization) = authorization {
+            request = request.header(AUTHORIZATION, authorization);
+        }
+        server.app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap().status()
+    }
+}
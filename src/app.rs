@@ -1,69 +1,259 @@
+use crate::admin::AdminControls;
+use crate::breaker::CircuitBreaker;
+use crate::compliance::Compliance;
 use crate::executor::TradeExecutor;
-use crate::model::{BlockEvent, RequestId};
-use crate::network::Network;
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::model::{BlockEvent, DecayCurve, RequestId};
+use crate::network::{BlockStream, Network};
+use crate::rebalancer::{Rebalancer, RouterBridge};
+use crate::reload::NetworkChanges;
+use crate::signer::Signers;
 use crate::solver::Solver;
-use crate::config::{ConfigFile, SolverConfig};
+use crate::status::SolverStatus;
+use crate::treasury::{self, Rebalance};
+use crate::config::{ConfigFile, NetworkConfig};
+use alloy::primitives::U256;
 use alloy::providers::DynProvider;
 use futures::StreamExt;
-use futures::future::try_join_all;
-use futures::stream::select_all;
+use futures::stream::{AbortHandle, Abortable, SelectAll};
 use moka::sync::Cache;
-use std::collections::HashMap;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+pub struct App {
+    signers: Signers,
+    solver_id: u8,
+    config: ConfigFile, // as applied, which lags the file for chains we failed to connect to
+    networks: HashMap<u64, Arc<Network<DynProvider>>>,
+    blocks: SelectAll<Abortable<BlockStream>>,
+    subscriptions: HashMap<u64, AbortHandle>, // aborting one ends that chain's block stream
+    solver: Solver<Network<DynProvider>>,
+    executor: TradeExecutor<DynProvider>,
+    health: Arc<Health>,
+    controls: Arc<AdminControls>,
+    metrics: Arc<Metrics>,
+    rebalancer: Rebalancer,
+    treasury_transfers: HashMap<u64, u64>, // block the last treasury transfer of each chain landed in
+    breaker: Arc<CircuitBreaker>,
+    inflight_requests: Cache<RequestId, ()>,
+}
 
-pub struct App {}
 impl App {
-    pub async fn start(networks: HashMap<u64, Network<DynProvider>>, config: &ConfigFile, solver_id: u8) -> eyre::Result<()> {
-        let block_numbers = networks.values().map(|network| network.stream_block_numbers());
-        let streams = try_join_all(block_numbers).await?;
-        let mut stream = Box::pin(select_all(streams));
-
-        // Get solver configuration or use defaults
-        let (threshold_multiplier, solver_name) = if let Some(solver_config) = &config.solver_config {
-            (solver_config.threshold_multiplier, format!("{}#{}", solver_config.solver_name, solver_id))
-        } else {
-            // Configure solver with competitive defaults - INVERTED for correct competitive behavior
-            match solver_id {
-                1 => (1.1, format!("AggressiveSolver#{}", solver_id)),    // Most aggressive - 10% down (90% of start price)
-                2 => (1.5, format!("ModerateSolver#{}", solver_id)),      // Moderate - 33% down (67% of start price)  
-                3 => (2.0, format!("ConservativeSolver#{}", solver_id)),  // Conservative - 50% down (50% of start price)
-                _ => panic!("Invalid solver_id: {}. Must be 1, 2, or 3", solver_id),
+    pub async fn start(signers: Signers, mut config_updates: watch::Receiver<ConfigFile>, solver_id: u8, metrics: Arc<Metrics>, health: Arc<Health>, status: Arc<SolverStatus>, controls: Arc<AdminControls>) -> eyre::Result<()> {
+        let config = config_updates.borrow_and_update().clone();
+        let networks = Network::create_many(&signers, &config.networks).await?;
+        let mut streams = Vec::new();
+        for (chain_id, network) in &networks {
+            streams.push((*chain_id, network.stream_block_numbers().await?));
+        }
+
+        let (threshold_multiplier, solver_name, decay_curve) = strategy(&config, solver_id);
+        let mut solver = Solver::from(&networks, threshold_multiplier, solver_name, decay_curve, metrics.clone(), status.clone(), controls.clone()).await?;
+        solver.set_fill_costs(fill_costs(&config));
+        solver.set_risk_limits(config.risk.clone());
+        solver.set_compliance(Compliance::load(&config.compliance)?);
+        solver.set_demo_mode(config.demo_mode);
+        let breaker = Arc::new(CircuitBreaker::new(controls.clone()));
+        breaker.set_config(config.circuit_breaker.clone());
+        let executor = TradeExecutor::new(&networks, metrics.clone(), status, solver.reservations(), solver.risk(), breaker.clone());
+        for chain_id in networks.keys() {
+            if let Some(state) = solver.state(*chain_id) {
+                health.state_fetched(*chain_id, state);
             }
+        }
+
+        let mut app = App {
+            signers,
+            solver_id,
+            config,
+            networks,
+            blocks: SelectAll::new(),
+            subscriptions: HashMap::new(),
+            solver,
+            executor,
+            health,
+            controls,
+            metrics,
+            rebalancer: Rebalancer::new(Box::new(RouterBridge)),
+            treasury_transfers: HashMap::new(),
+            breaker,
+            // we pull new chain state every block, so inflight requests may not have been
+            // completed yet, so we don't want to attempt to execute them again and waste gas.
+            // if they're still there after 30s we can reattempt
+            inflight_requests: Cache::builder().max_capacity(1000).time_to_live(Duration::from_secs(30)).build(),
         };
+        for (chain_id, blocks) in streams {
+            app.subscribe(chain_id, blocks);
+        }
 
-        let mut solver = Solver::from(&networks, threshold_multiplier, solver_name).await?;
-        let executor = TradeExecutor::new(&networks);
-
-        // we pull new chain state every block, so inflight requests may not have been
-        // completed yet, so we don't want to attempt to execute them again and waste gas.
-        // if they're still there after 30s we can reattempt
-        let mut inflight_requests: Cache<RequestId, ()> = Cache::builder().max_capacity(1000).time_to_live(Duration::from_secs(30)).build();
-
-        while let Some(BlockEvent { chain_id, .. }) = stream.next().await {
-            // // Add solver-specific delay to simulate real-world processing differences
-            // let delay_ms = match solver_id {
-            //     1 => 0,   // AggressiveSolver: fastest processing (immediate)
-            //     2 => 100, // ModerateSolver: moderate delay  
-            //     3 => 250, // ConservativeSolver: significant delay
-            //     _ => 500,
-            // };
-            // if delay_ms > 0 {
-            //     tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-            // }
-            
-            let trades = solver.fetch_state(chain_id, &inflight_requests).await?;
-            if !trades.is_empty() {
-                println!("executing {} trades from chain {}", trades.len(), chain_id);
-                executor.execute(trades, &mut inflight_requests).await;
-                
-                // // ✅ IMMEDIATE STATE REFRESH: Update all solver states after execution
-                // // This helps other solvers quickly detect completed trades
-                // for &refresh_chain in networks.keys() {
-                //     let _ = solver.refresh_chain_state(refresh_chain).await;
-                // }
+        // config changes are applied between blocks, so never in the middle of solving or executing
+        loop {
+            tokio::select! {
+                event = app.blocks.next() => match event {
+                    Some(event) => app.on_block(event).await,
+                    None => break,
+                },
+                Ok(()) = config_updates.changed() => {
+                    let config = config_updates.borrow_and_update().clone();
+                    app.reload(config).await;
+                }
             }
         }
 
         eyre::bail!("stream of blocks ended unexpectedly");
     }
+
+    async fn on_block(&mut self, event: BlockEvent) {
+        let BlockEvent { chain_id, block_number, .. } = event;
+        self.health.block_seen(chain_id, block_number);
+        // // Add solver-specific delay to simulate real-world processing differences
+        // let delay_ms = match solver_id {
+        //     1 => 0,   // AggressiveSolver: fastest processing (immediate)
+        //     2 => 100, // ModerateSolver: moderate delay
+        //     3 => 250, // ConservativeSolver: significant delay
+        //     _ => 500,
+        // };
+        // if delay_ms > 0 {
+        //     tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        // }
+
+        // a flaky RPC shouldn't take the whole solver down; we report it via readiness and try again next block
+        let trades = match self.solver.fetch_state(&event, &self.inflight_requests).await {
+            Ok(trades) => trades,
+            Err(e) => {
+                warn!(chain_id, error = %e, "failed to fetch state");
+                self.health.state_fetch_failed(chain_id, &e);
+                return;
+            }
+        };
+        if let Some(state) = self.solver.state(chain_id) {
+            self.health.state_fetched(chain_id, state);
+            self.breaker.balance_observed(chain_id, state.token_balance);
+            let awaiting_refund: Vec<RequestId> = state.transfers.iter().map(|transfer| transfer.request_id).collect();
+            self.breaker.refunds_received(chain_id, &awaiting_refund, Instant::now());
+        }
+        if !trades.is_empty() {
+            info!(chain_id, trades = trades.len(), "executing trades");
+            let count = trades.len();
+            self.controls.executing_started(count);
+            self.executor.execute(trades, &mut self.inflight_requests).await;
+            self.controls.executing_finished(count);
+        }
+
+        // trades have gone out by now, so whatever is left above or below the bounds is ours to move, unless
+        // the circuit breaker has halted the chain or the balance we read predates our last transfer
+        if let (Some(network), Some(state)) = (self.networks.get(&chain_id), self.solver.state(chain_id))
+            && !self.controls.is_tripped(chain_id)
+            && self.treasury_transfers.get(&chain_id).is_none_or(|landed_in| state.block_number >= *landed_in)
+        {
+            match treasury::rebalance(network, self.solver.reservations().available(chain_id, state), &self.metrics).await {
+                Ok(Some((rebalance, landed_in))) => {
+                    self.treasury_transfers.insert(chain_id, landed_in);
+                    if let Rebalance::Sweep(amount) = rebalance {
+                        self.breaker.sent(chain_id, amount);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!(chain_id, error = %e, "failed to rebalance with treasury"),
+            }
+        }
+
+        // chains the circuit breaker has halted neither send nor receive inventory
+        let balances: HashMap<u64, U256> = self.networks.keys().filter(|id| !self.controls.is_tripped(**id)).filter_map(|id| self.solver.state(*id).map(|state| (*id, self.solver.reservations().available(*id, state)))).collect();
+        let fulfilled: HashSet<RequestId> = self.networks.keys().filter_map(|id| self.solver.state(*id)).flat_map(|state| state.already_fulfilled.iter().copied()).collect();
+        for sent in self.rebalancer.rebalance(&self.config.rebalancer, &self.networks, &self.config.networks, &balances, &fulfilled, &self.metrics).await {
+            self.breaker.sent(sent.src_chain_id, sent.amount + sent.fee);
+        }
+    }
+
+    // Applies a new config without losing the auctions on chains that stay configured
+    async fn reload(&mut self, config: ConfigFile) {
+        let (threshold_multiplier, solver_name, decay_curve) = strategy(&config, self.solver_id);
+        self.solver.set_strategy(threshold_multiplier, solver_name, decay_curve);
+        self.solver.set_fill_costs(fill_costs(&config));
+        self.solver.set_risk_limits(config.risk.clone());
+        self.solver.set_demo_mode(config.demo_mode);
+        match Compliance::load(&config.compliance) {
+            Ok(compliance) => self.solver.set_compliance(compliance),
+            Err(e) => warn!(error = %e, "failed to load address lists, keeping the previous ones"),
+        }
+        self.breaker.set_config(config.circuit_breaker.clone());
+
+        let changes = NetworkChanges::between(&self.config.networks, &config.networks);
+        for chain_id in changes.disconnect {
+            self.disconnect(chain_id);
+        }
+
+        let mut applied = config.networks.clone();
+        for network_config in config.networks.iter().filter(|network| changes.connect.contains(&network.chain_id)) {
+            let chain_id = network_config.chain_id;
+            if let Err(e) = self.connect(network_config).await {
+                warn!(chain_id, error = %e, "failed to connect to chain, keeping its previous config");
+                match self.config.networks.iter().find(|previous| previous.chain_id == chain_id) {
+                    Some(previous) => applied.iter_mut().filter(|network| network.chain_id == chain_id).for_each(|network| *network = previous.clone()),
+                    None => applied.retain(|network| network.chain_id != chain_id),
+                }
+            }
+        }
+
+        self.health.set_networks(&applied);
+        self.config = ConfigFile { networks: applied, ..config };
+        info!(chains = self.networks.len(), "applied config");
+    }
+
+    // Connects to a new chain, or reconnects to one whose settings changed, and starts solving on it
+    async fn connect(&mut self, config: &NetworkConfig) -> eyre::Result<()> {
+        let network = Arc::new(Network::create(&self.signers.for_network(config)?, config).await?);
+        let blocks = network.stream_block_numbers().await?;
+        self.solver.add_chain(config.chain_id, network.clone()).await?;
+        self.subscribe(config.chain_id, blocks);
+        self.networks.insert(config.chain_id, network);
+        self.executor.set_networks(&self.networks);
+        Ok(())
+    }
+
+    fn disconnect(&mut self, chain_id: u64) {
+        if let Some(subscription) = self.subscriptions.remove(&chain_id) {
+            subscription.abort();
+        }
+        self.networks.remove(&chain_id);
+        self.executor.set_networks(&self.networks);
+        self.solver.remove_chain(chain_id);
+        info!(chain_id, "disconnected from chain");
+    }
+
+    // Replaces any previous stream of blocks for the chain
+    fn subscribe(&mut self, chain_id: u64, blocks: BlockStream) {
+        let (subscription, registration) = AbortHandle::new_pair();
+        if let Some(previous) = self.subscriptions.insert(chain_id, subscription) {
+            previous.abort();
+        }
+        self.blocks.push(Abortable::new(blocks, registration));
+    }
+}
+
+// Get solver configuration or use defaults
+fn strategy(config: &ConfigFile, solver_id: u8) -> (f64, String, DecayCurve) {
+    let (threshold_multiplier, solver_name) = if let Some(solver_config) = &config.solver_config {
+        (solver_config.threshold_multiplier, format!("{}#{}", solver_config.solver_name, solver_id))
+    } else {
+        // Configure solver with competitive defaults - INVERTED for correct competitive behavior
+        match solver_id {
+            1 => (1.1, format!("AggressiveSolver#{}", solver_id)),    // Most aggressive - 10% down (90% of start price)
+            2 => (1.5, format!("ModerateSolver#{}", solver_id)),      // Moderate - 33% down (67% of start price)
+            3 => (2.0, format!("ConservativeSolver#{}", solver_id)),  // Conservative - 50% down (50% of start price)
+            _ => panic!("Invalid solver_id: {}. Must be 1, 2, or 3", solver_id),
+        }
+    };
+
+    let decay_curve = config.solver_config.as_ref().map(|c| c.decay_curve.clone()).unwrap_or_default();
+    (threshold_multiplier, solver_name, decay_curve)
+}
+
+fn fill_costs(config: &ConfigFile) -> HashMap<u64, U256> {
+    config.networks.iter().map(|network| (network.chain_id, network.fill_cost)).collect()
 }
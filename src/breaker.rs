@@ -0,0 +1,217 @@
+use crate::admin::AdminControls;
+use crate::config::CircuitBreakerConfig;
+use crate::model::RequestId;
+use alloy::primitives::{B256, U256};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tracing::warn;
+
+// Halts trading on a chain when something looks wrong there: fills keep reverting, tokens go missing,
+// or refunds we're owed don't arrive. A tripped chain stays halted until an operator resets it through
+// the admin API, as whatever tripped it is unlikely to fix itself.
+pub(crate) struct CircuitBreaker {
+    controls: Arc<AdminControls>,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Default)]
+struct BreakerState {
+    config: CircuitBreakerConfig,
+    consecutive_failures: HashMap<u64, u32>,
+    expected_balance: HashMap<u64, U256>, // the balance we last saw, less what we've sent since
+    unexplained_loss: HashMap<u64, U256>, // how far balances have fallen short of what we expected
+    unpaid: HashMap<RequestId, (u64, Instant)>, // fills awaiting their refund, with their source chain and when we made them
+    tripped: HashSet<u64>, // chains we've tripped, to notice when they get reset
+}
+
+impl CircuitBreaker {
+    pub fn new(controls: Arc<AdminControls>) -> Self {
+        Self {
+            controls,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    pub fn set_config(&self, config: CircuitBreakerConfig) {
+        self.lock().config = config;
+    }
+
+    pub fn trade_succeeded(&self, request_id: RequestId, src_chain_id: u64, dest_chain_id: u64, amount: U256) {
+        let mut state = self.lock();
+        state.consecutive_failures.remove(&dest_chain_id);
+        state.unpaid.insert(request_id, (src_chain_id, Instant::now()));
+        sent(&mut state, dest_chain_id, amount);
+    }
+
+    pub fn trade_reverted(&self, dest_chain_id: u64) {
+        let mut state = self.lock();
+        let failures = state.consecutive_failures.entry(dest_chain_id).or_default();
+        *failures += 1;
+        let failures = *failures;
+        if state.config.max_consecutive_failures.is_some_and(|max| failures >= max) {
+            self.trip(&mut state, dest_chain_id, format!("{} fills in a row reverted", failures));
+        }
+    }
+
+    // Tokens we've sent from the chain, or may have if we couldn't find out whether the transaction landed
+    pub fn sent(&self, chain_id: u64, amount: U256) {
+        sent(&mut self.lock(), chain_id, amount);
+    }
+
+    // Compares the balance we just fetched with what it should be after what we've sent since the last one.
+    // Anything above that is fine (refunds, top-ups, moves in); anything below it is unexplained.
+    pub fn balance_observed(&self, chain_id: u64, balance: U256) {
+        let mut state = self.lock();
+        if let Some(expected) = state.expected_balance.insert(chain_id, balance)
+            && balance < expected
+        {
+            let loss = state.unexplained_loss.entry(chain_id).or_default();
+            *loss = loss.saturating_add(expected - balance);
+            let loss = *loss;
+            if state.config.max_balance_loss.is_some_and(|max| loss > max) {
+                self.trip(&mut state, chain_id, format!("token balance is {} below what we expected", loss));
+            }
+        }
+    }
+
+    // Fills from the chain it no longer lists as awaiting a refund have been paid out; any left unpaid
+    // for too long by `now` trip the chain that owes them
+    pub fn refunds_received(&self, src_chain_id: u64, awaiting_refund: &[RequestId], now: Instant) {
+        let mut state = self.lock();
+        state
+            .unpaid
+            .retain(|request_id, (chain_id, _)| *chain_id != src_chain_id || awaiting_refund.contains(request_id));
+
+        let Some(max_age) = state.config.max_refund_age_secs else {
+            return;
+        };
+        let overdue = state
+            .unpaid
+            .iter()
+            .map(|(request_id, (chain_id, filled_at))| (*request_id, *chain_id, now.saturating_duration_since(*filled_at).as_secs()))
+            .find(|(_, chain_id, age)| *chain_id == src_chain_id && *age > max_age)
+            .map(|(request_id, _, age)| (request_id, age));
+        if let Some((request_id, age)) = overdue {
+            self.trip(&mut state, src_chain_id, format!("refund for {} unpaid after {}s", B256::from(request_id), age));
+        }
+    }
+
+    fn trip(&self, state: &mut BreakerState, chain_id: u64, reason: String) {
+        if state.tripped.insert(chain_id) {
+            warn!(chain_id, %reason, "circuit breaker tripped, halting trading on chain until reset");
+            self.controls.trip(chain_id, reason);
+        }
+    }
+
+    // Whatever tripped a chain that's since been reset starts over
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        let reset: Vec<u64> = state.tripped.iter().copied().filter(|chain_id| !self.controls.is_tripped(*chain_id)).collect();
+        for chain_id in reset {
+            state.tripped.remove(&chain_id);
+            state.consecutive_failures.remove(&chain_id);
+            state.expected_balance.remove(&chain_id);
+            state.unexplained_loss.remove(&chain_id);
+            let now = Instant::now();
+            state.unpaid.values_mut().filter(|(src_chain_id, _)| *src_chain_id == chain_id).for_each(|(_, filled_at)| *filled_at = now);
+        }
+        state
+    }
+}
+
+fn sent(state: &mut BreakerState, chain_id: u64, amount: U256) {
+    if let Some(expected) = state.expected_balance.get_mut(&chain_id) {
+        *expected = expected.saturating_sub(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::admin::AdminControls;
+    use crate::breaker::CircuitBreaker;
+    use crate::config::CircuitBreakerConfig;
+    use crate::util::test::generate_request_id;
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn repeated_reverts_trip_the_chain_until_it_is_reset() {
+        // given
+        let controls = Arc::new(AdminControls::default());
+        let breaker = breaker(&controls, CircuitBreakerConfig {
+            max_consecutive_failures: Some(2),
+            ..Default::default()
+        });
+
+        // when
+        breaker.trade_reverted(1);
+        breaker.trade_succeeded(generate_request_id(), 2, 1, U256::ZERO);
+        breaker.trade_reverted(1);
+        let after_a_success = controls.is_tripped(1);
+        breaker.trade_reverted(1);
+        let tripped = controls.is_tripped(1);
+        controls.reset_circuit(1);
+        breaker.trade_reverted(1);
+
+        // then
+        assert_that!(after_a_success).is_false();
+        assert_that!(tripped).is_true();
+        assert_that!(controls.is_tripped(1)).is_false();
+    }
+
+    #[test]
+    fn only_unexplained_balance_drops_count_as_losses() {
+        // given
+        let controls = Arc::new(AdminControls::default());
+        let breaker = breaker(&controls, CircuitBreakerConfig {
+            max_balance_loss: Some(U256::from(100)),
+            ..Default::default()
+        });
+
+        // when
+        breaker.balance_observed(1, U256::from(1000));
+        breaker.sent(1, U256::from(400));
+        breaker.balance_observed(1, U256::from(560)); // 40 unexplained
+        breaker.balance_observed(1, U256::from(900)); // refunded
+        let within_limit = controls.is_tripped(1);
+        breaker.balance_observed(1, U256::from(830)); // 110 unexplained in total
+
+        // then
+        assert_that!(within_limit).is_false();
+        assert_that!(controls.is_tripped(1)).is_true();
+        assert_that!(controls.report().tripped_chains[&1].as_str()).is_equal_to("token balance is 110 below what we expected");
+    }
+
+    #[test]
+    fn overdue_refunds_trip_the_chain_that_owes_them() {
+        // given
+        let controls = Arc::new(AdminControls::default());
+        let breaker = breaker(&controls, CircuitBreakerConfig {
+            max_refund_age_secs: Some(1),
+            ..Default::default()
+        });
+        let (paid, unpaid) = (generate_request_id(), generate_request_id());
+        breaker.trade_succeeded(paid, 1, 2, U256::from(10));
+        breaker.trade_succeeded(unpaid, 1, 2, U256::from(10));
+
+        // when
+        breaker.refunds_received(1, &[paid, unpaid], Instant::now());
+        let while_recent = controls.is_tripped(1);
+        breaker.refunds_received(1, &[unpaid], Instant::now() + Duration::from_secs(2));
+
+        // then
+        assert_that!(while_recent).is_false();
+        assert_that!(controls.is_tripped(1)).is_true();
+        assert_that!(controls.is_tripped(2)).is_false();
+    }
+
+    fn breaker(controls: &Arc<AdminControls>, config: CircuitBreakerConfig) -> CircuitBreaker {
+        let breaker = CircuitBreaker::new(controls.clone());
+        breaker.set_config(config);
+        breaker
+    }
+}
@@ -0,0 +1,99 @@
+use crate::config::ComplianceConfig;
+use crate::model::SkipReason;
+use alloy::primitives::Address;
+use eyre::WrapErr;
+use shellexpand::tilde;
+use std::collections::HashSet;
+use std::fs;
+
+// Which swap requests we may serve, by the addresses involved. A request is refused if its sender or
+// recipient is on the deny-list, or, if there's an allow-list, if its sender (the integrator) isn't on it.
+#[derive(Debug, Default)]
+pub(crate) struct Compliance {
+    deny: Option<HashSet<Address>>,
+    allow: Option<HashSet<Address>>,
+}
+
+impl Compliance {
+    pub fn load(config: &ComplianceConfig) -> eyre::Result<Self> {
+        Ok(Self {
+            deny: config.deny_list.as_deref().map(load_address_list).transpose()?,
+            allow: config.allow_list.as_deref().map(load_address_list).transpose()?,
+        })
+    }
+
+    pub fn check(&self, sender: Address, recipient: Address) -> Result<(), SkipReason> {
+        if self.deny.as_ref().is_some_and(|deny| deny.contains(&sender) || deny.contains(&recipient)) {
+            return Err(SkipReason::DeniedAddress);
+        }
+        if self.allow.as_ref().is_some_and(|allow| !allow.contains(&sender)) {
+            return Err(SkipReason::NotAllowListed);
+        }
+        Ok(())
+    }
+}
+
+// One address per line; blank lines and anything after a `#` are ignored
+pub(crate) fn load_address_list(path: &str) -> eyre::Result<HashSet<Address>> {
+    let contents = fs::read_to_string(tilde(path).as_ref()).wrap_err_with(|| format!("failed to read address list at {}", path))?;
+    let mut addresses = HashSet::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let address = line.parse().wrap_err_with(|| format!("{}:{}: `{}` is not an address", path, i + 1, line))?;
+        addresses.insert(address);
+    }
+    Ok(addresses)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compliance::Compliance;
+    use crate::config::ComplianceConfig;
+    use crate::model::SkipReason;
+    use crate::util::test::generate_address;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn requests_are_checked_against_both_lists() {
+        // given
+        let (denied, integrator, user) = (generate_address(), generate_address(), generate_address());
+        let deny_list = write_list("deny", &format!("# sanctioned\n{}\n\n", denied));
+        let allow_list = write_list("allow", &format!("{} # our integrator\n{}\n", integrator, denied));
+        let compliance = Compliance::load(&ComplianceConfig {
+            deny_list: Some(deny_list.clone()),
+            allow_list: Some(allow_list.clone()),
+        })
+        .unwrap();
+        fs::remove_file(deny_list).unwrap();
+        fs::remove_file(allow_list).unwrap();
+
+        // then
+        assert_that!(compliance.check(integrator, user)).is_ok();
+        assert_that!(compliance.check(integrator, denied)).is_err_containing(SkipReason::DeniedAddress);
+        assert_that!(compliance.check(denied, user)).is_err_containing(SkipReason::DeniedAddress);
+        assert_that!(compliance.check(user, integrator)).is_err_containing(SkipReason::NotAllowListed);
+        assert_that!(Compliance::default().check(user, denied)).is_ok();
+    }
+
+    #[test]
+    fn malformed_lists_are_rejected_with_the_line() {
+        let path = write_list("malformed", "0x00000000000000000000000000000000000000aa\nnot-an-address\n");
+        let result = Compliance::load(&ComplianceConfig {
+            deny_list: Some(path.clone()),
+            allow_list: None,
+        });
+        fs::remove_file(&path).unwrap();
+        assert_that!(result.unwrap_err().to_string()).is_equal_to(format!("{}:2: `not-an-address` is not an address", path));
+    }
+
+    fn write_list(name: &str, contents: &str) -> String {
+        let path = std::env::temp_dir().join(format!("solver-{}-{}.txt", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+}
@@ -1,34 +1,123 @@
-use clap::Parser;
+use crate::compliance::load_address_list;
+use crate::logging::LogFormat;
+use crate::model::{AuctionClock, DecayCurve, FULL_BPS};
+use alloy::primitives::{Address, U256};
+use clap::{ArgGroup, Parser, Subcommand};
+use eyre::{WrapErr, eyre};
 use serde::Deserialize;
+use serde_json::{Map, Value};
 use shellexpand::tilde;
 use std::fs;
-use alloy::primitives::U256;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::info;
+use url::Url;
+
+// Environment variables starting with this override fields of the config file, with `__` separating
+// the path segments, e.g. `SOLVER__NETWORKS__0__RPC_URL` sets the `rpc_url` of the first network
+const ENV_OVERRIDE_PREFIX: &str = "SOLVER__";
 
 #[derive(Parser, Debug)]
+#[command(subcommand_negates_reqs = true)]
+#[command(group(ArgGroup::new("signer").args(["private_key", "keystore_path", "remote_signer_url"])))]
 pub(crate) struct CliArgs {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(short = 'c', long = "config", env = "SOLVER_CONFIG_PATH", default_value = "~/.solver/config.json")]
-    pub config_path: String,
+    pub config_path: String, // JSON, TOML or YAML, picked by the file extension
 
+    // at most one of a private key, a keystore or a remote signer; it signs for every network without a signer of its own
     #[arg(short = 's', long = "private-key", env = "SOLVER_PRIVATE_KEY")]
-    pub private_key: String,
+    pub private_key: Option<String>,
+
+    #[arg(long = "keystore", env = "SOLVER_KEYSTORE_PATH", requires = "keystore_password_file")]
+    pub keystore_path: Option<String>, // encrypted JSON keystore
+
+    #[arg(long = "keystore-password-file", env = "SOLVER_KEYSTORE_PASSWORD_FILE")]
+    pub keystore_password_file: Option<String>,
+
+    #[arg(long = "remote-signer-url", env = "SOLVER_REMOTE_SIGNER_URL", requires = "remote_signer_address")]
+    pub remote_signer_url: Option<String>, // JSON-RPC endpoint serving `eth_signTransaction`
+
+    #[arg(long = "remote-signer-address", env = "SOLVER_REMOTE_SIGNER_ADDRESS")]
+    pub remote_signer_address: Option<Address>, // the account the remote signer signs for
 
     #[arg(short = 'p', long = "port", env = "SOLVER_PORT", default_value = "8081")]
     pub port: u16,
 
     #[arg(short = 'i', long = "solver-id", env = "SOLVER_ID", default_value = "1")]
     pub solver_id: u8,
+
+    #[arg(long = "admin-token", env = "SOLVER_ADMIN_TOKEN")]
+    pub admin_token: Option<String>, // bearer token for the admin API; it's disabled if unset
+
+    #[arg(long = "log-format", env = "SOLVER_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    #[arg(long = "log-level", env = "SOLVER_LOG", default_value = "info")]
+    pub log_level: String, // `EnvFilter` directives, so levels can be set per module
+}
+
+// Where the key that signs the solver's transactions lives
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum SignerConfig {
+    PrivateKey { private_key: String },
+    Keystore { path: String, password_file: String },
+    Remote { url: String, address: Address },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum Command {
+    /// Work with the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Work with the circuit breaker of a running solver, through its admin API
+    Circuit {
+        #[command(subcommand)]
+        command: CircuitCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum ConfigCommand {
+    /// Load the config file, apply any environment overrides and validate it, without starting the solver
+    Validate,
+}
+
+#[derive(Subcommand, Debug)]
+pub(crate) enum CircuitCommand {
+    /// Resume trading on a chain the circuit breaker has halted
+    Reset { chain_id: u64 },
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub(crate) struct ConfigFile {
     pub networks: Vec<NetworkConfig>,
     pub solver_config: Option<SolverConfig>,
+    #[serde(default)]
+    pub health: HealthConfig,
+    #[serde(default)]
+    pub rebalancer: RebalancerConfig,
+    #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub compliance: ComplianceConfig,
+    #[serde(default)]
+    pub demo_mode: bool, // run auctions for requests already fulfilled when the solver started, so demos have bidding to show
 }
 
 #[derive(Deserialize, Debug, Clone)]
 pub(crate) struct SolverConfig {
     pub threshold_multiplier: f64, // Multiplier for min_allowed_cost (e.g., 2.0 = 2x threshold)
     pub solver_name: String,
+    #[serde(default)]
+    pub decay_curve: DecayCurve, // Shape of the auction price curve; linear if omitted
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -37,12 +126,536 @@ pub(crate) struct NetworkConfig {
     pub rpc_url: String,
     pub rusd_address: String,
     pub router_address: String,
+    #[serde(default)]
+    pub auction_clock: AuctionClock, // "timestamp" or "block_number"
+    #[serde(default)]
+    pub min_native_balance: U256, // in wei; below this the solver isn't ready as it can't pay for gas
+    #[serde(default)]
+    pub signer: Option<SignerConfig>, // signs for this chain only, instead of the signer given on the command line
+    #[serde(default)]
+    pub treasury: Option<TreasuryConfig>,
+    #[serde(default)]
+    pub inventory: Option<InventoryConfig>, // takes part in cross-chain rebalancing if set
+    #[serde(default)]
+    pub fill_cost: U256, // estimate of what a fill on this chain costs us in tokens, gas included
+}
+
+// A cold wallet holding the float the hot signer of a chain doesn't need to have on hand
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct TreasuryConfig {
+    pub address: String,
+    pub min_token_balance: U256, // below this we top up from the treasury, through an allowance it gave the signer
+    pub max_token_balance: U256, // above this we sweep the excess to the treasury
+}
+
+// The band the token balance of a chain is kept in by moving inventory between chains
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct InventoryConfig {
+    pub min_token_balance: U256,    // below this we move tokens in from other chains
+    pub target_token_balance: U256, // what we move tokens in up to, and what other chains can draw down to
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct RebalancerConfig {
+    #[serde(default = "default_rebalance_fee_bps")]
+    pub fee_bps: u64, // what we offer solvers to fill our own swap requests, in basis points of the amount moved
+    #[serde(default = "default_rebalance_cooldown_secs")]
+    pub cooldown_secs: u64, // how long to wait for a move to be filled before its chains take part in another
+}
+
+impl Default for RebalancerConfig {
+    fn default() -> Self {
+        Self {
+            fee_bps: default_rebalance_fee_bps(),
+            cooldown_secs: default_rebalance_cooldown_secs(),
+        }
+    }
+}
+
+fn default_rebalance_fee_bps() -> u64 {
+    10
+}
+
+fn default_rebalance_cooldown_secs() -> u64 {
+    600
+}
+
+// Caps on how much the solver puts at risk; each is unlimited if unset
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct RiskConfig {
+    #[serde(default)]
+    pub max_trade_amount: Option<U256>, // most tokens a single fill may send
+    #[serde(default)]
+    pub max_chain_exposure: Option<U256>, // most tokens any one source chain may owe us in refunds
+    #[serde(default)]
+    pub max_total_exposure: Option<U256>, // most tokens all chains together may owe us in refunds
+    #[serde(default)]
+    pub max_trades_per_minute: Option<u32>,
+}
+
+// When to halt trading on a chain until an operator resets it; each check is off if unset
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct CircuitBreakerConfig {
+    #[serde(default)]
+    pub max_consecutive_failures: Option<u32>, // fills in a row that reverted on the chain
+    #[serde(default)]
+    pub max_balance_loss: Option<U256>, // tokens gone from the chain beyond what we know we sent
+    #[serde(default)]
+    pub max_refund_age_secs: Option<u64>, // how long the chain may owe us the refund for a fill
+}
+
+// Files listing addresses whose swap requests we refuse or, for an allow-list, the only senders we serve.
+// Both are reloaded along with the config file whenever they change.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub(crate) struct ComplianceConfig {
+    #[serde(default)]
+    pub deny_list: Option<String>, // senders and recipients we never serve
+    #[serde(default)]
+    pub allow_list: Option<String>, // if set, the integrators whose requests we serve, by sender
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct HealthConfig {
+    #[serde(default = "default_max_block_age_secs")]
+    pub max_block_age_secs: u64, // a chain without a new block for longer than this is unhealthy
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            max_block_age_secs: default_max_block_age_secs(),
+        }
+    }
+}
+
+fn default_max_block_age_secs() -> u64 {
+    60
+}
+
+impl CliArgs {
+    pub fn signer(&self) -> Option<SignerConfig> {
+        if let Some(private_key) = &self.private_key {
+            return Some(SignerConfig::PrivateKey {
+                private_key: private_key.clone(),
+            });
+        }
+        if let (Some(path), Some(password_file)) = (&self.keystore_path, &self.keystore_password_file) {
+            return Some(SignerConfig::Keystore {
+                path: path.clone(),
+                password_file: password_file.clone(),
+            });
+        }
+        if let (Some(url), Some(address)) = (&self.remote_signer_url, self.remote_signer_address) {
+            return Some(SignerConfig::Remote { url: url.clone(), address });
+        }
+        None
+    }
+}
+
+pub(crate) fn load_config_file(path: &str) -> eyre::Result<ConfigFile> {
+    info!(path, "loading config file");
+    let expanded = tilde(path).into_owned();
+    let contents = fs::read_to_string(&expanded).wrap_err_with(|| format!("failed to read config file at {}", path))?;
+    let config = parse_config(&expanded, &contents, std::env::vars()).wrap_err_with(|| format!("invalid config file at {}", path))?;
+    Ok(config)
+}
+
+// Parses the config in whichever format `path` has, applies the overrides from `env` and validates the result
+fn parse_config(path: &str, contents: &str, env: impl IntoIterator<Item = (String, String)>) -> eyre::Result<ConfigFile> {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+    let mut value: Value = match extension {
+        "json" => serde_json::from_str(contents)?,
+        "toml" => toml::from_str(contents)?,
+        "yaml" | "yml" => serde_yaml::from_str(contents)?,
+        _ => eyre::bail!("unsupported config format `{}`, expected one of json, toml, yaml or yml", extension),
+    };
+    apply_env_overrides(&mut value, env)?;
+
+    let config: ConfigFile = serde_path_to_error::deserialize(value).map_err(|err| eyre!("{}: {}", err.path(), err.inner()))?;
+    config.validate()?;
+    Ok(config)
+}
+
+fn apply_env_overrides(config: &mut Value, env: impl IntoIterator<Item = (String, String)>) -> eyre::Result<()> {
+    // sorted by path, with list indices in numeric order, so that e.g. networks can be appended to the list
+    // one after another, field by field
+    let mut overrides: Vec<_> = env
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(ENV_OVERRIDE_PREFIX))
+        .map(|(key, value)| (key[ENV_OVERRIDE_PREFIX.len()..].split("__").map(str::to_lowercase).collect::<Vec<String>>(), key, value))
+        .collect();
+    overrides.sort_by_cached_key(|(path, _, _)| path.iter().map(|segment| (segment.parse::<usize>().ok(), segment.clone())).collect::<Vec<_>>());
+
+    for (path, key, value) in overrides {
+        set_path(config, &path, value).wrap_err_with(|| format!("failed to apply override {}", key))?;
+    }
+    Ok(())
+}
+
+fn set_path(node: &mut Value, path: &[String], value: String) -> eyre::Result<()> {
+    let Some((segment, rest)) = path.split_first() else {
+        *node = override_value(node, value);
+        return Ok(());
+    };
+    if segment.is_empty() {
+        eyre::bail!("empty path segment");
+    }
+
+    let index = segment.parse::<usize>().ok();
+    if node.is_null() {
+        *node = if index.is_some() { Value::Array(vec![]) } else { Value::Object(Map::new()) };
+    }
+    let child = match (node, index) {
+        (Value::Object(fields), _) => fields.entry(segment.clone()).or_insert(Value::Null),
+        (Value::Array(items), Some(index)) => {
+            if index == items.len() {
+                items.push(Value::Null);
+            }
+            let len = items.len();
+            items.get_mut(index).ok_or_else(|| eyre!("index {} is out of bounds for a list of {}", index, len))?
+        }
+        (Value::Array(_), None) => eyre::bail!("`{}` is not an index into a list", segment),
+        (_, _) => eyre::bail!("`{}` can't be set on a value that is neither a table nor a list", segment),
+    };
+    set_path(child, rest, value)
 }
 
-pub(crate) fn load_config_file(cli: &CliArgs) -> ConfigFile {
-    println!("loading config file {}", cli.config_path);
-    match fs::read(tilde(&cli.config_path).into_owned()) {
-        Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|_| panic!("failed to parse config file at {}", cli.config_path)),
-        Err(err) => panic!("failed to read config file at {}: {:?}", cli.config_path, err.to_string()),
+// Environment variables are untyped, so strings stay strings and anything else is read as JSON
+fn override_value(current: &Value, value: String) -> Value {
+    if current.is_string() {
+        return Value::String(value);
+    }
+    serde_json::from_str(&value).unwrap_or(Value::String(value))
+}
+
+impl ConfigFile {
+    // Checks everything that deserialising can't, reporting every problem rather than just the first
+    pub fn validate(&self) -> eyre::Result<()> {
+        let mut errors = vec![];
+        if self.networks.is_empty() {
+            errors.push("networks: at least one network is required".to_string());
+        }
+
+        for (i, network) in self.networks.iter().enumerate() {
+            if let Some(first) = self.networks[..i].iter().position(|other| other.chain_id == network.chain_id) {
+                errors.push(format!("networks[{}].chain_id: {} is already used by networks[{}]", i, network.chain_id, first));
+            }
+            if let Err(e) = validate_rpc_url(&network.rpc_url) {
+                errors.push(format!("networks[{}].rpc_url: {}", i, e));
+            }
+            if let Err(e) = validate_address(&network.rusd_address) {
+                errors.push(format!("networks[{}].rusd_address: {}", i, e));
+            }
+            if let Err(e) = validate_address(&network.router_address) {
+                errors.push(format!("networks[{}].router_address: {}", i, e));
+            }
+            if let Some(SignerConfig::Remote { url, .. }) = &network.signer
+                && let Err(e) = validate_signer_url(url)
+            {
+                errors.push(format!("networks[{}].signer.url: {}", i, e));
+            }
+            if let Some(treasury) = &network.treasury {
+                if let Err(e) = validate_address(&treasury.address) {
+                    errors.push(format!("networks[{}].treasury.address: {}", i, e));
+                }
+                if treasury.min_token_balance > treasury.max_token_balance {
+                    errors.push(format!("networks[{}].treasury: min_token_balance is above max_token_balance", i));
+                }
+            }
+            if let Some(inventory) = &network.inventory
+                && inventory.min_token_balance > inventory.target_token_balance
+            {
+                errors.push(format!("networks[{}].inventory: min_token_balance is above target_token_balance", i));
+            }
+        }
+
+        if let Some(solver_config) = &self.solver_config {
+            if !(solver_config.threshold_multiplier.is_finite() && solver_config.threshold_multiplier > 0.0) {
+                errors.push(format!("solver_config.threshold_multiplier: must be greater than 0, got {}", solver_config.threshold_multiplier));
+            }
+            if let Err(e) = solver_config.decay_curve.validate() {
+                errors.push(format!("solver_config.decay_curve: {}", e));
+            }
+        }
+
+        if self.health.max_block_age_secs == 0 {
+            errors.push("health.max_block_age_secs: must be greater than 0".to_string());
+        }
+        for (field, limit) in [
+            ("max_trade_amount", self.risk.max_trade_amount),
+            ("max_chain_exposure", self.risk.max_chain_exposure),
+            ("max_total_exposure", self.risk.max_total_exposure),
+            ("max_trades_per_minute", self.risk.max_trades_per_minute.map(U256::from)),
+        ] {
+            if limit == Some(U256::ZERO) {
+                errors.push(format!("risk.{}: must be greater than 0, leave it out for no limit", field));
+            }
+        }
+        for (field, threshold) in [
+            ("max_consecutive_failures", self.circuit_breaker.max_consecutive_failures.map(U256::from)),
+            ("max_refund_age_secs", self.circuit_breaker.max_refund_age_secs.map(U256::from)),
+        ] {
+            if threshold == Some(U256::ZERO) {
+                errors.push(format!("circuit_breaker.{}: must be greater than 0, leave it out to turn the check off", field));
+            }
+        }
+        for (field, path) in [("deny_list", &self.compliance.deny_list), ("allow_list", &self.compliance.allow_list)] {
+            if let Some(path) = path
+                && let Err(e) = load_address_list(path)
+            {
+                errors.push(format!("compliance.{}: {}", field, e));
+            }
+        }
+        if self.rebalancer.fee_bps > FULL_BPS {
+            errors.push(format!("rebalancer.fee_bps: must be at most {}, got {}", FULL_BPS, self.rebalancer.fee_bps));
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(eyre!("{}", errors.join("\n"))) }
+    }
+}
+
+// The solver only speaks websockets to the chains, as it subscribes to new blocks
+fn validate_rpc_url(rpc_url: &str) -> eyre::Result<()> {
+    let url = Url::parse(rpc_url).map_err(|e| eyre!("`{}` is not a valid URL: {}", rpc_url, e))?;
+    if url.scheme() != "ws" && url.scheme() != "wss" {
+        eyre::bail!("`{}` uses the {} scheme, expected ws or wss", rpc_url, url.scheme());
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        eyre::bail!("`{}` has no host", rpc_url);
+    }
+    Ok(())
+}
+
+fn validate_signer_url(url: &str) -> eyre::Result<()> {
+    let parsed = Url::parse(url).map_err(|e| eyre!("`{}` is not a valid URL: {}", url, e))?;
+    if parsed.scheme() != "http" && parsed.scheme() != "https" {
+        eyre::bail!("`{}` uses the {} scheme, expected http or https", url, parsed.scheme());
+    }
+    Ok(())
+}
+
+// Mixed-case addresses must carry a valid EIP-55 checksum; all lower or upper case ones have none to check
+fn validate_address(address: &str) -> eyre::Result<()> {
+    let Some(digits) = address.strip_prefix("0x") else {
+        eyre::bail!("`{}` must start with 0x", address);
+    };
+    let parsed = Address::from_str(address).map_err(|_| eyre!("`{}` is not a 20 byte hex address", address))?;
+    let mixed_case = digits.chars().any(|c| c.is_ascii_lowercase()) && digits.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case && parsed.to_checksum(None) != address {
+        eyre::bail!("`{}` has an invalid checksum, expected `{}`", address, parsed.to_checksum(None));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{RiskConfig, SignerConfig, parse_config};
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+
+    const JSON: &str = r#"{
+        "networks": [{
+            "chain_id": 43113,
+            "rpc_url": "wss://avalanche-fuji-c-chain-rpc.publicnode.com",
+            "rusd_address": "0x908e1D85604E0e9e703d52D18f3f3f604Fe7Bb1b",
+            "router_address": "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"
+        }],
+        "solver_config": { "threshold_multiplier": 2.0, "solver_name": "fast" }
+    }"#;
+
+    const TOML: &str = r#"
+        [[networks]]
+        chain_id = 43113
+        rpc_url = "wss://avalanche-fuji-c-chain-rpc.publicnode.com"
+        rusd_address = "0x908e1D85604E0e9e703d52D18f3f3f604Fe7Bb1b"
+        router_address = "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"
+
+        [solver_config]
+        threshold_multiplier = 2.0
+        solver_name = "fast"
+    "#;
+
+    const YAML: &str = r#"
+networks:
+  - chain_id: 43113
+    rpc_url: wss://avalanche-fuji-c-chain-rpc.publicnode.com
+    rusd_address: "0x908e1D85604E0e9e703d52D18f3f3f604Fe7Bb1b"
+    router_address: "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"
+solver_config:
+  threshold_multiplier: 2.0
+  solver_name: fast
+"#;
+
+    #[test]
+    fn every_format_is_read_the_same_way() {
+        for (path, contents) in [("config.json", JSON), ("config.toml", TOML), ("config.yaml", YAML), ("config.yml", YAML)] {
+            // when
+            let config = parse_config(path, contents, vec![]).unwrap();
+
+            // then
+            assert_that!(config.networks).has_length(1);
+            assert_that!(config.networks[0].chain_id).is_equal_to(43113);
+            assert_that!(config.solver_config.unwrap().solver_name).is_equal_to("fast".to_string());
+        }
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        let result = parse_config("config.ini", JSON, vec![]);
+        assert_that!(result.unwrap_err().to_string()).contains("unsupported config format `ini`");
+    }
+
+    #[test]
+    fn environment_variables_override_any_field() {
+        // given
+        let env = vec![
+            env("SOLVER__NETWORKS__0__RPC_URL", "wss://other.example.com"),
+            env("SOLVER__NETWORKS__0__MIN_NATIVE_BALANCE", "1000"),
+            env("SOLVER__SOLVER_CONFIG__SOLVER_NAME", "1"),
+            env("SOLVER_PORT", "8080"),
+        ];
+
+        // when
+        let config = parse_config("config.json", JSON, env).unwrap();
+
+        // then
+        assert_that!(config.networks[0].rpc_url).is_equal_to("wss://other.example.com".to_string());
+        assert_that!(config.networks[0].min_native_balance).is_equal_to(U256::from(1000));
+        assert_that!(config.solver_config.unwrap().solver_name).is_equal_to("1".to_string());
+    }
+
+    #[test]
+    fn environment_variables_can_add_a_network() {
+        // given
+        let env = vec![
+            env("SOLVER__NETWORKS__1__CHAIN_ID", "84532"),
+            env("SOLVER__NETWORKS__1__RPC_URL", "wss://base-sepolia-rpc.publicnode.com"),
+            env("SOLVER__NETWORKS__1__RUSD_ADDRESS", "0x908e1D85604E0e9e703d52D18f3f3f604Fe7Bb1b"),
+            env("SOLVER__NETWORKS__1__ROUTER_ADDRESS", "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"),
+        ];
+
+        // when
+        let config = parse_config("config.json", JSON, env).unwrap();
+
+        // then
+        assert_that!(config.networks).has_length(2);
+        assert_that!(config.networks[1].chain_id).is_equal_to(84532);
+    }
+
+    #[test]
+    fn environment_variables_can_add_networks_past_the_tenth() {
+        // given
+        let env: Vec<_> = (1..=10)
+            .flat_map(|i| {
+                [
+                    env(&format!("SOLVER__NETWORKS__{}__CHAIN_ID", i), &(84530 + i).to_string()),
+                    env(&format!("SOLVER__NETWORKS__{}__RPC_URL", i), "wss://base-sepolia-rpc.publicnode.com"),
+                    env(&format!("SOLVER__NETWORKS__{}__RUSD_ADDRESS", i), "0x908e1D85604E0e9e703d52D18f3f3f604Fe7Bb1b"),
+                    env(&format!("SOLVER__NETWORKS__{}__ROUTER_ADDRESS", i), "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"),
+                ]
+            })
+            .collect();
+
+        // when
+        let config = parse_config("config.json", JSON, env).unwrap();
+
+        // then
+        assert_that!(config.networks).has_length(11);
+        assert_that!(config.networks[2].chain_id).is_equal_to(84532);
+        assert_that!(config.networks[10].chain_id).is_equal_to(84540);
+    }
+
+    #[test]
+    fn overrides_past_the_end_of_a_list_are_rejected() {
+        let result = parse_config("config.json", JSON, vec![env("SOLVER__NETWORKS__5__CHAIN_ID", "1")]);
+        assert_that!(format!("{:#}", result.unwrap_err())).contains("index 5 is out of bounds for a list of 1");
+    }
+
+    #[test]
+    fn type_errors_point_at_the_field() {
+        let result = parse_config("config.json", JSON, vec![env("SOLVER__NETWORKS__0__CHAIN_ID", "\"fuji\"")]);
+        assert_that!(result.unwrap_err().to_string()).starts_with("networks[0].chain_id: invalid type");
+    }
+
+    #[test]
+    fn validation_reports_every_problem_with_its_field() {
+        // given
+        let env = vec![
+            env("SOLVER__NETWORKS__1__CHAIN_ID", "43113"),
+            env("SOLVER__NETWORKS__1__RPC_URL", "https://base-sepolia-rpc.publicnode.com"),
+            env("SOLVER__NETWORKS__1__RUSD_ADDRESS", "0x908E1D85604E0e9e703d52D18f3f3f604Fe7Bb1b"),
+            env("SOLVER__NETWORKS__1__ROUTER_ADDRESS", "3dD1a497846d060Dce130B67b22E1F9DeE18c051"),
+            env("SOLVER__SOLVER_CONFIG__THRESHOLD_MULTIPLIER", "0"),
+        ];
+
+        // when
+        let error = parse_config("config.json", JSON, env).unwrap_err().to_string();
+
+        // then
+        assert_that!(error).contains("networks[1].chain_id: 43113 is already used by networks[0]");
+        assert_that!(error).contains("networks[1].rpc_url: `https://base-sepolia-rpc.publicnode.com` uses the https scheme, expected ws or wss");
+        assert_that!(error).contains("networks[1].rusd_address: `0x908E1D85604E0e9e703d52D18f3f3f604Fe7Bb1b` has an invalid checksum");
+        assert_that!(error).contains("networks[1].router_address: `3dD1a497846d060Dce130B67b22E1F9DeE18c051` must start with 0x");
+        assert_that!(error).contains("solver_config.threshold_multiplier: must be greater than 0");
+    }
+
+    #[test]
+    fn networks_can_have_their_own_signer_and_treasury() {
+        // given
+        let env = vec![
+            env("SOLVER__NETWORKS__0__SIGNER__TYPE", "keystore"),
+            env("SOLVER__NETWORKS__0__SIGNER__PATH", "/secrets/fuji.json"),
+            env("SOLVER__NETWORKS__0__SIGNER__PASSWORD_FILE", "/secrets/fuji-password"),
+            env("SOLVER__NETWORKS__0__TREASURY__ADDRESS", "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"),
+            env("SOLVER__NETWORKS__0__TREASURY__MIN_TOKEN_BALANCE", "1000"),
+            env("SOLVER__NETWORKS__0__TREASURY__MAX_TOKEN_BALANCE", "5000"),
+        ];
+
+        // when
+        let config = parse_config("config.json", JSON, env).unwrap();
+
+        // then
+        let network = &config.networks[0];
+        assert_that!(network.signer).is_equal_to(Some(SignerConfig::Keystore {
+            path: "/secrets/fuji.json".to_string(),
+            password_file: "/secrets/fuji-password".to_string(),
+        }));
+        assert_that!(network.treasury.as_ref().unwrap().max_token_balance).is_equal_to(U256::from(5000));
+    }
+
+    #[test]
+    fn treasuries_must_allow_some_balance() {
+        let env = vec![
+            env("SOLVER__NETWORKS__0__TREASURY__ADDRESS", "0x3dD1a497846d060Dce130B67b22E1F9DeE18c051"),
+            env("SOLVER__NETWORKS__0__TREASURY__MIN_TOKEN_BALANCE", "2"),
+            env("SOLVER__NETWORKS__0__TREASURY__MAX_TOKEN_BALANCE", "1"),
+        ];
+        let result = parse_config("config.json", JSON, env);
+        assert_that!(result.unwrap_err().to_string()).contains("networks[0].treasury: min_token_balance is above max_token_balance");
+    }
+
+    #[test]
+    fn risk_limits_are_unlimited_unless_set() {
+        // given
+        let env = vec![env("SOLVER__RISK__MAX_TRADE_AMOUNT", "1000"), env("SOLVER__RISK__MAX_TRADES_PER_MINUTE", "0")];
+
+        // when
+        let unset = parse_config("config.json", JSON, vec![]).unwrap().risk;
+        let error = parse_config("config.json", JSON, env).unwrap_err().to_string();
+
+        // then
+        assert_that!(unset).is_equal_to(RiskConfig::default());
+        assert_that!(error).contains("risk.max_trades_per_minute: must be greater than 0");
+    }
+
+    #[test]
+    fn lower_case_addresses_have_no_checksum_to_validate() {
+        let env = vec![env("SOLVER__NETWORKS__0__RUSD_ADDRESS", "0x908e1d85604e0e9e703d52d18f3f3f604fe7bb1b")];
+        assert_that!(parse_config("config.json", JSON, env)).is_ok();
+    }
+
+    fn env(key: &str, value: &str) -> (String, String) {
+        (key.to_string(), value.to_string())
     }
 }
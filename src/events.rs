@@ -0,0 +1,116 @@
+use crate::model::{RequestId, SkipReason};
+use crate::status::decimal;
+use alloy::primitives::{B256, TxHash, U256};
+use serde::Serialize;
+
+// Structured events describing the solver's decisions as they happen, streamed by the `ApiServer`
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum SolverEvent {
+    RequestDiscovered {
+        request_id: B256,
+        src_chain_id: u64,
+        dest_chain_id: u64,
+        #[serde(serialize_with = "decimal")]
+        amount_out: U256,
+        #[serde(serialize_with = "decimal")]
+        solver_fee: U256,
+    },
+    AuctionStarted {
+        request_id: B256,
+        dest_chain_id: u64,
+        start_time: u64,
+        end_time: u64,
+        #[serde(serialize_with = "decimal")]
+        start_fee: U256,
+        #[serde(serialize_with = "decimal")]
+        reserve_fee: U256,
+    },
+    PriceTick {
+        request_id: B256,
+        dest_chain_id: u64,
+        now: u64,
+        #[serde(serialize_with = "decimal")]
+        current_fee: U256,
+        #[serde(serialize_with = "decimal")]
+        execution_threshold: U256,
+    },
+    Decision {
+        request_id: B256,
+        dest_chain_id: u64,
+        #[serde(flatten)]
+        decision: Decision,
+    },
+    TxSubmitted {
+        request_id: B256,
+        dest_chain_id: u64,
+        tx_hash: TxHash,
+    },
+    TxMined {
+        request_id: B256,
+        dest_chain_id: u64,
+        tx_hash: TxHash,
+        success: bool,
+        gas_used: u64,
+    },
+    RefundReceived {
+        request_id: B256,
+        src_chain_id: u64,
+    },
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub(crate) enum Decision {
+    Fill {
+        #[serde(serialize_with = "decimal")]
+        price: U256,
+    },
+    Skip {
+        reason: SkipReason,
+    },
+}
+
+impl SolverEvent {
+    pub fn fill(request_id: &RequestId, dest_chain_id: u64, price: U256) -> Self {
+        SolverEvent::Decision {
+            request_id: B256::from(*request_id),
+            dest_chain_id,
+            decision: Decision::Fill { price },
+        }
+    }
+
+    pub fn skip(request_id: &RequestId, dest_chain_id: u64, reason: SkipReason) -> Self {
+        SolverEvent::Decision {
+            request_id: B256::from(*request_id),
+            dest_chain_id,
+            decision: Decision::Skip { reason },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::{Decision, SolverEvent};
+    use alloy::primitives::{B256, U256};
+    use speculoos::assert_that;
+
+    #[test]
+    fn decisions_serialise_flat_with_their_type() {
+        // given
+        let event = SolverEvent::Decision {
+            request_id: B256::ZERO,
+            dest_chain_id: 2,
+            decision: Decision::Fill { price: U256::from(150) },
+        };
+
+        // when
+        let json = serde_json::to_value(&event).unwrap();
+
+        // then
+        assert_that!(json["type"]).is_equal_to(serde_json::json!("decision"));
+        assert_that!(json["decision"]).is_equal_to(serde_json::json!("fill"));
+        assert_that!(json["price"]).is_equal_to(serde_json::json!("150"));
+        assert_that!(json["dest_chain_id"]).is_equal_to(serde_json::json!(2));
+    }
+}
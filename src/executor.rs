@@ -1,75 +1,143 @@
-use crate::eth::ERC20FaucetToken::ERC20FaucetTokenInstance;
-use crate::eth::Router::RouterInstance;
+use crate::breaker::CircuitBreaker;
+use crate::events::SolverEvent;
+use crate::metrics::Metrics;
 use crate::model::{RequestId, Trade};
 use crate::network::Network;
-use crate::util::normalise_chain_id;
-use alloy::primitives::TxHash;
+use crate::reservations::Reservations;
+use crate::risk::RiskLimits;
+use crate::status::{SolverStatus, TradeStatus};
+use crate::util::{normalise_chain_id, request_span};
+use alloy::network::ReceiptResponse;
+use alloy::primitives::{B256, U256};
 use alloy::providers::Provider;
+use alloy::rpc::types::TransactionReceipt;
 use moka::sync::Cache;
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::{Instrument, error, info, warn};
 
-pub(crate) struct TradeExecutor<'a, P> {
-    routers: HashMap<u64, &'a RouterInstance<P>>,
-    tokens: HashMap<u64, &'a ERC20FaucetTokenInstance<P>>,
+pub(crate) struct TradeExecutor<P> {
+    networks: HashMap<u64, Arc<Network<P>>>,
+    metrics: Arc<Metrics>,
+    status: Arc<SolverStatus>,
+    reservations: Arc<Reservations>,
+    risk: Arc<Mutex<RiskLimits>>,
+    breaker: Arc<CircuitBreaker>,
 }
 
-impl<'a, P: Provider> TradeExecutor<'a, P> {
-    pub fn new(networks: &'a HashMap<u64, Network<P>>) -> Self {
-        let routers = networks.iter().map(|(chain_id, net)| (*chain_id, &net.router)).collect();
-        let tokens = networks.iter().map(|(chain_id, net)| (*chain_id, &net.token)).collect();
-        Self { routers, tokens }
+impl<P: Provider> TradeExecutor<P> {
+    pub fn new(networks: &HashMap<u64, Arc<Network<P>>>, metrics: Arc<Metrics>, status: Arc<SolverStatus>, reservations: Arc<Reservations>, risk: Arc<Mutex<RiskLimits>>, breaker: Arc<CircuitBreaker>) -> Self {
+        Self {
+            networks: networks.clone(),
+            metrics,
+            status,
+            reservations,
+            risk,
+            breaker,
+        }
+    }
+    // Called when the config is reloaded, so trades go through the current connections
+    pub fn set_networks(&mut self, networks: &HashMap<u64, Arc<Network<P>>>) {
+        self.networks = networks.clone();
     }
     pub async fn execute(&self, trades: Vec<Trade>, in_flight: &mut Cache<RequestId, ()>) {
         for trade in trades {
-            // first we add the trade to the cache so that we don't retry it in the next block
-            // (before it's been finalised, potentially)
-            in_flight.insert(trade.request_id, ());
+            let span = request_span(trade.request_id);
+            self.execute_trade(trade, in_flight).instrument(span).await;
+        }
+    }
+    async fn execute_trade(&self, trade: Trade, in_flight: &mut Cache<RequestId, ()>) {
+        // first we add the trade to the cache so that we don't retry it in the next block
+        // (before it's been finalised, potentially)
+        in_flight.insert(trade.request_id, ());
 
-            // then we get the contract bindings for the destination chain
-            let router = self
-                .routers
-                .get(&normalise_chain_id(trade.dest_chain_id))
-                .expect("somehow didn't have a router binding for a solved trade");
-            let token = self
-                .tokens
-                .get(&normalise_chain_id(trade.dest_chain_id))
-                .expect("somehow didn't have a token binding for a solved trade");
+        // then we get the contract bindings for the destination chain
+        let network = self
+            .networks
+            .get(&normalise_chain_id(trade.dest_chain_id))
+            .expect("somehow didn't have contract bindings for a solved trade");
+        let (router, token) = (&network.router, &network.token);
 
-            // in theory, we shouldn't need to wait until the next block because txs will be processed in nonce order
-            // but for whatever reason this doesn't seem to be the case :(
-            let approve: eyre::Result<TxHash> = async {
-                let tx = token.approve(*router.address(), trade.swap_amount).send().await?;
-                let receipt = tx.watch().await?;
-                Ok(receipt)
-            }
-            .await;
-            match approve {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("error approving trade: {e}");
+        let chain_id = normalise_chain_id(trade.dest_chain_id);
+        self.metrics.trade_attempted(chain_id);
+        self.status.trade_submitted(&trade);
+
+        // in theory, we shouldn't need to wait until the next block because txs will be processed in nonce order
+        // but for whatever reason this doesn't seem to be the case :(
+        let approve: eyre::Result<TransactionReceipt> = async {
+            let tx = token.approve(*router.address(), trade.swap_amount).send().await?;
+            Ok(tx.get_receipt().await?)
+        }
+        .await;
+        match approve {
+            Ok(receipt) => {
+                self.record_gas(chain_id, &receipt);
+                if !receipt.status() {
+                    warn!("approval for trade reverted");
                 }
             }
+            Err(e) => {
+                warn!(error = %e, "error approving trade");
+            }
+        }
 
-            // actually send the funds via the router contract
-            let relay: eyre::Result<TxHash> = async {
-                let tx = router
-                    .relayTokens(
-                        trade.token_addr,
-                        trade.recipient_addr,
-                        trade.swap_amount,
-                        trade.request_id.into(),
-                        trade.src_chain_id,
-                    )
-                    .send()
-                    .await?;
-                let receipt = tx.watch().await?;
-                Ok(receipt)
+        // actually send the funds via the router contract
+        let relay: eyre::Result<TransactionReceipt> = async {
+            let tx = router
+                .relayTokens(
+                    trade.token_addr,
+                    trade.recipient_addr,
+                    trade.swap_amount,
+                    trade.request_id.into(),
+                    trade.src_chain_id,
+                )
+                .send()
+                .await?;
+            self.status.publish(SolverEvent::TxSubmitted {
+                request_id: B256::from(trade.request_id),
+                dest_chain_id: chain_id,
+                tx_hash: *tx.tx_hash(),
+            });
+            let receipt = tx.get_receipt().await?;
+            self.status.publish(SolverEvent::TxMined {
+                request_id: B256::from(trade.request_id),
+                dest_chain_id: chain_id,
+                tx_hash: receipt.transaction_hash,
+                success: receipt.status(),
+                gas_used: receipt.gas_used(),
+            });
+            Ok(receipt)
+        }
+        .await;
+        match relay {
+            Ok(receipt) if receipt.status() => {
+                self.record_gas(chain_id, &receipt);
+                self.metrics.trade_succeeded(chain_id);
+                self.breaker.trade_succeeded(trade.request_id, normalise_chain_id(trade.src_chain_id), chain_id, trade.swap_amount);
+                self.status.trade_finished(&trade.request_id, TradeStatus::Succeeded { tx_hash: receipt.transaction_hash });
+                info!(amount = %trade.swap_amount, chain_id, "successfully traded")
+            }
+            Ok(receipt) => {
+                self.record_gas(chain_id, &receipt);
+                self.reservations.release(&trade.request_id);
+                self.risk.lock().expect("risk limits lock poisoned").release(&trade.request_id);
+                self.breaker.trade_reverted(chain_id);
+                self.metrics.trade_failed(chain_id, "reverted");
+                self.status.trade_finished(&trade.request_id, TradeStatus::Failed { reason: "reverted".to_string() });
+                error!(amount = %trade.swap_amount, chain_id, tx_hash = %receipt.transaction_hash, "error trading: transaction reverted")
             }
-            .await;
-            match relay {
-                Ok(_) => println!("successfully traded {} on {}", trade.swap_amount, trade.dest_chain_id),
-                Err(e) => println!("error trading {} on {}: {}", trade.swap_amount, trade.dest_chain_id, e),
+            // the fill may still land, so its tokens stay reserved; once it's no longer in flight we may send it
+            // again, which reuses this reservation rather than holding its tokens twice
+            Err(e) => {
+                self.breaker.sent(chain_id, trade.swap_amount);
+                self.metrics.trade_failed(chain_id, "rpc_error");
+                self.status.trade_finished(&trade.request_id, TradeStatus::Failed { reason: e.to_string() });
+                error!(amount = %trade.swap_amount, chain_id, error = %e, "error trading")
             }
         }
     }
+    fn record_gas(&self, chain_id: u64, receipt: &TransactionReceipt) {
+        let wei = U256::from(receipt.gas_used()) * U256::from(receipt.effective_gas_price());
+        self.metrics.gas_spent(chain_id, wei);
+    }
 }
@@ -0,0 +1,264 @@
+use crate::config::NetworkConfig;
+use crate::model::ChainState;
+use alloy::primitives::U256;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+// Tracks the health of every configured chain so that orchestrators can tell
+// whether the solver is actually able to solve, rather than just running
+pub(crate) struct Health {
+    max_block_age: Duration,
+    chains: RwLock<HashMap<u64, ChainHealth>>,
+}
+
+struct ChainHealth {
+    min_native_balance: U256,
+    last_block: Option<(u64, Instant)>,
+    native_balance: Option<U256>,
+    rpc_error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ReadinessReport {
+    pub ready: bool,
+    pub chains: BTreeMap<u64, ChainReport>,
+}
+
+#[derive(Serialize, Debug)]
+pub(crate) struct ChainReport {
+    pub ready: bool,
+    pub head_block: Option<u64>,
+    pub last_block_age_secs: Option<u64>,
+    pub block_fresh: bool,
+    pub rpc_ok: bool,
+    pub rpc_error: Option<String>,
+    pub native_balance: Option<String>,
+    pub min_native_balance: String,
+    pub funded: bool,
+}
+
+impl Health {
+    pub fn new(max_block_age: Duration, networks: &[NetworkConfig]) -> Self {
+        let chains = networks
+            .iter()
+            .map(|config| (config.chain_id, ChainHealth::new(config.min_native_balance)))
+            .collect();
+
+        Self {
+            max_block_age,
+            chains: RwLock::new(chains),
+        }
+    }
+
+    // Follows a config reload: chains keep what we've seen of them, new ones start out unready
+    pub fn set_networks(&self, networks: &[NetworkConfig]) {
+        let mut chains = self.chains.write().expect("health lock poisoned");
+        chains.retain(|chain_id, _| networks.iter().any(|config| config.chain_id == *chain_id));
+        for config in networks {
+            chains.entry(config.chain_id).or_insert_with(|| ChainHealth::new(config.min_native_balance)).min_native_balance = config.min_native_balance;
+        }
+    }
+
+    pub fn block_seen(&self, chain_id: u64, block_number: u64) {
+        self.update(chain_id, |chain| chain.last_block = Some((block_number, Instant::now())));
+    }
+
+    pub fn state_fetched(&self, chain_id: u64, state: &ChainState) {
+        self.update(chain_id, |chain| {
+            chain.native_balance = Some(state.native_balance);
+            chain.rpc_error = None;
+        });
+    }
+
+    pub fn state_fetch_failed(&self, chain_id: u64, error: &eyre::Report) {
+        self.update(chain_id, |chain| chain.rpc_error = Some(error.to_string()));
+    }
+
+    pub fn report(&self, now: Instant) -> ReadinessReport {
+        let chains: BTreeMap<u64, ChainReport> = self
+            .chains
+            .read()
+            .expect("health lock poisoned")
+            .iter()
+            .map(|(chain_id, chain)| (*chain_id, chain.report(now, self.max_block_age)))
+            .collect();
+
+        ReadinessReport {
+            ready: chains.values().all(|chain| chain.ready),
+            chains,
+        }
+    }
+
+    fn update(&self, chain_id: u64, f: impl FnOnce(&mut ChainHealth)) {
+        if let Some(chain) = self.chains.write().expect("health lock poisoned").get_mut(&chain_id) {
+            f(chain)
+        }
+    }
+}
+
+impl ChainHealth {
+    fn new(min_native_balance: U256) -> Self {
+        Self {
+            min_native_balance,
+            last_block: None,
+            native_balance: None,
+            rpc_error: None,
+        }
+    }
+
+    fn report(&self, now: Instant, max_block_age: Duration) -> ChainReport {
+        let block_age = self.last_block.map(|(_, seen_at)| now.saturating_duration_since(seen_at));
+        let block_fresh = block_age.is_some_and(|age| age <= max_block_age);
+        // no balance yet means we haven't managed to fetch any state
+        let rpc_ok = self.rpc_error.is_none() && self.native_balance.is_some();
+        let funded = self.native_balance.is_some_and(|balance| balance > self.min_native_balance);
+
+        ChainReport {
+            ready: block_fresh && rpc_ok && funded,
+            head_block: self.last_block.map(|(block_number, _)| block_number),
+            last_block_age_secs: block_age.map(|age| age.as_secs()),
+            block_fresh,
+            rpc_ok,
+            rpc_error: self.rpc_error.clone(),
+            native_balance: self.native_balance.map(|balance| balance.to_string()),
+            min_native_balance: self.min_native_balance.to_string(),
+            funded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::NetworkConfig;
+    use crate::health::Health;
+    use crate::model::{AuctionClock, ChainState};
+    use crate::util::test::network_config;
+    use alloy::primitives::{Address, U256};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn chains_that_recently_produced_a_block_and_are_funded_are_ready() {
+        // given
+        let health = Health::new(Duration::from_secs(60), &[network(1, 10)]);
+
+        // when
+        health.block_seen(1, 100);
+        health.state_fetched(1, &state(U256::from(11)));
+        let report = health.report(Instant::now());
+
+        // then
+        assert_that!(report.ready).is_true();
+        assert_that!(report.chains[&1].head_block).is_equal_to(Some(100));
+    }
+
+    #[test]
+    fn chains_without_recent_blocks_are_not_ready() {
+        // given
+        let health = Health::new(Duration::from_secs(60), &[network(1, 0)]);
+        health.block_seen(1, 100);
+        health.state_fetched(1, &state(U256::from(1)));
+
+        // when
+        let report = health.report(Instant::now() + Duration::from_secs(61));
+
+        // then
+        assert_that!(report.ready).is_false();
+        assert_that!(report.chains[&1].block_fresh).is_false();
+        assert_that!(report.chains[&1].rpc_ok).is_true();
+    }
+
+    #[test]
+    fn chains_that_have_never_produced_a_block_are_not_ready() {
+        // given
+        let health = Health::new(Duration::from_secs(60), &[network(1, 0), network(2, 0)]);
+        health.block_seen(1, 100);
+        health.state_fetched(1, &state(U256::from(1)));
+
+        // when
+        let report = health.report(Instant::now());
+
+        // then
+        assert_that!(report.ready).is_false();
+        assert_that!(report.chains[&1].ready).is_true();
+        assert_that!(report.chains[&2].ready).is_false();
+    }
+
+    #[test]
+    fn rpc_failures_make_a_chain_unready_until_the_next_successful_fetch() {
+        // given
+        let health = Health::new(Duration::from_secs(60), &[network(1, 0)]);
+        health.block_seen(1, 100);
+        health.state_fetched(1, &state(U256::from(1)));
+
+        // when
+        health.state_fetch_failed(1, &eyre::eyre!("connection reset"));
+        let failed = health.report(Instant::now());
+        health.state_fetched(1, &state(U256::from(1)));
+        let recovered = health.report(Instant::now());
+
+        // then
+        assert_that!(failed.ready).is_false();
+        assert_that!(failed.chains[&1].rpc_error).is_equal_to(Some("connection reset".to_string()));
+        assert_that!(recovered.ready).is_true();
+    }
+
+    #[test]
+    fn signers_at_or_below_the_minimum_balance_are_not_ready() {
+        // given
+        let health = Health::new(Duration::from_secs(60), &[network(1, 10)]);
+        health.block_seen(1, 100);
+
+        // when
+        health.state_fetched(1, &state(U256::from(10)));
+        let report = health.report(Instant::now());
+
+        // then
+        assert_that!(report.ready).is_false();
+        assert_that!(report.chains[&1].funded).is_false();
+    }
+
+    #[test]
+    fn reloaded_networks_keep_their_health_and_new_ones_start_unready() {
+        // given
+        let health = Health::new(Duration::from_secs(60), &[network(1, 0), network(2, 0)]);
+        health.block_seen(1, 100);
+        health.state_fetched(1, &state(U256::from(5)));
+
+        // when
+        health.set_networks(&[network(1, 5), network(3, 0)]);
+        let report = health.report(Instant::now());
+
+        // then
+        assert_that!(report.chains.keys().copied().collect::<Vec<_>>()).is_equal_to(vec![1, 3]);
+        assert_that!(report.chains[&1].head_block).is_equal_to(Some(100));
+        assert_that!(report.chains[&1].funded).is_false();
+        assert_that!(report.chains[&3].ready).is_false();
+    }
+
+    fn network(chain_id: u64, min_native_balance: u64) -> NetworkConfig {
+        NetworkConfig {
+            min_native_balance: U256::from(min_native_balance),
+            ..network_config(chain_id)
+        }
+    }
+
+    fn state(native_balance: U256) -> ChainState {
+        ChainState {
+            token_addr: Address::ZERO,
+            own_addr: Address::ZERO,
+            native_balance,
+            token_balance: U256::ZERO,
+            transfers: vec![],
+            already_fulfilled: vec![],
+            active_auctions: HashMap::new(),
+            block_number: 0,
+            block_timestamp: 0,
+            auction_clock: AuctionClock::Timestamp,
+        }
+    }
+}
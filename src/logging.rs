@@ -0,0 +1,21 @@
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+// `filter` takes `EnvFilter` directives, e.g. `info,onlyswaps_solver::solver=debug` to get debug logs
+// from the solver only, or `info,[request{request_id=0x...}]=trace` to follow a single request
+pub(crate) fn init(format: LogFormat, filter: &str) -> eyre::Result<()> {
+    let filter = EnvFilter::try_new(filter)?;
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    }
+    .map_err(|e| eyre::eyre!("failed to initialise logging: {e}"))
+}
@@ -1,37 +1,85 @@
+mod admin;
 mod api;
 mod app;
+mod breaker;
+mod compliance;
 mod config;
 mod eth;
+mod events;
 mod executor;
+mod health;
+mod logging;
+mod metrics;
 mod model;
 mod network;
+mod rebalancer;
+mod reload;
+mod request_blocks;
+mod request_checks;
+mod reservations;
+mod risk;
+mod scheduler;
+mod selection;
+mod signer;
+mod skips;
 mod solver;
+mod status;
+mod treasury;
 mod util;
 mod drand;
 
+use crate::admin::AdminControls;
 use crate::api::ApiServer;
 use crate::app::App;
-use crate::config::{CliArgs, ConfigFile, load_config_file};
-use crate::network::Network;
+use crate::config::{CircuitCommand, CliArgs, Command, ConfigCommand, ConfigFile, load_config_file};
+use crate::health::Health;
+use crate::metrics::Metrics;
+use crate::reload::ConfigReloader;
+use crate::signer::{Signers, SolverSigner};
+use crate::status::SolverStatus;
 use clap::Parser;
 use dotenv::dotenv;
 use eyre::eyre;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
 
 #[tokio::main]
 async fn main() -> eyre::Result<()> {
     dotenv().ok();
     let cli = CliArgs::parse();
-    let config: ConfigFile = load_config_file(&cli);
-    let networks = Network::create_many(&cli.private_key, &config.networks).await?;
+    logging::init(cli.log_format, &cli.log_level)?;
+    if let Some(Command::Circuit { command: CircuitCommand::Reset { chain_id } }) = cli.command {
+        admin::reset_circuit_remotely(cli.port, cli.admin_token.as_deref(), chain_id).await?;
+        println!("circuit breaker reset for chain {}", chain_id);
+        return Ok(());
+    }
+    let config: ConfigFile = load_config_file(&cli.config_path)?;
+    if let Some(Command::Config { command: ConfigCommand::Validate }) = cli.command {
+        println!("config file at {} is valid", cli.config_path);
+        return Ok(());
+    }
+
+    // networks without a signer of their own use the one from the command line
+    let default_signer = cli.signer().map(|config| SolverSigner::load(&config)).transpose()?;
+    if let Some(signer) = &default_signer {
+        info!(address = %signer.address(), "loaded default signer");
+    }
+    let signers = Signers::new(default_signer);
 
-    // start some healthcheck and signal handlers
-    let api_server = ApiServer::new(cli.port);
+    // start some healthcheck, metrics, introspection, admin, config reload and signal handlers
+    let metrics = Arc::new(Metrics::new());
+    let health = Arc::new(Health::new(Duration::from_secs(config.health.max_block_age_secs), &config.networks));
+    let status = Arc::new(SolverStatus::default());
+    let controls = Arc::new(AdminControls::default());
+    let (config_reloader, config_updates) = ConfigReloader::new(cli.config_path.clone(), config.clone());
+    let api_server = ApiServer::new(cli.port, cli.admin_token.clone(), metrics.clone(), health.clone(), status.clone(), controls.clone())?;
     let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
     let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
 
     // listen for alllll the things!
     tokio::select! {
-        res = App::start(networks, &config, cli.solver_id) => {
+        res = App::start(signers, config_updates, cli.solver_id, metrics, health, status, controls) => {
             match res {
                 Ok(_) => Err(eyre!("event listener stopped unexpectedly")),
                 Err(e) => Err(eyre!("event listener stopped unexpectedly: {}", e))
@@ -45,18 +93,25 @@ async fn main() -> eyre::Result<()> {
             }
         }
 
+        res = config_reloader.start() => {
+            match res {
+                Ok(_) => Err(eyre!("config reloader stopped unexpectedly")),
+                Err(e) => Err(eyre!("config reloader stopped unexpectedly: {}", e))
+            }
+        }
+
         _ = sigterm.recv() => {
-            println!("received SIGTERM, shutting down...");
+            info!("received SIGTERM, shutting down...");
             Ok(())
         },
 
         _ = sigint.recv() => {
-            println!("received SIGINT, shutting down...");
+            info!("received SIGINT, shutting down...");
             Ok(())
         },
 
         _ = tokio::signal::ctrl_c() => {
-            println!("received ctrl+c, shutting down...");
+            info!("received ctrl+c, shutting down...");
             Ok(())
         },
     }
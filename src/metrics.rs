@@ -0,0 +1,204 @@
+use crate::model::ChainState;
+use crate::skips::SkipReport;
+use alloy::primitives::U256;
+use prometheus::core::Collector;
+use prometheus::{CounterVec, Encoder, GaugeVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+
+// Prometheus metrics shared between the solver, the trade executor and the API server
+pub(crate) struct Metrics {
+    registry: Registry,
+    token_balance: GaugeVec,
+    native_balance: GaugeVec,
+    head_block: IntGaugeVec,
+    active_auctions: IntGaugeVec,
+    unfulfilled_requests: IntGaugeVec,
+    trades_attempted: IntCounterVec,
+    trades_succeeded: IntCounterVec,
+    trades_failed: IntCounterVec,
+    gas_spent: CounterVec,
+    drand_fetch_failures: IntCounter,
+    skipped_requests: IntGaugeVec,
+    skipped_volume: GaugeVec,
+    treasury_transfers: IntCounterVec,
+    treasury_volume: CounterVec,
+    rebalances: IntCounterVec,
+    rebalance_volume: CounterVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let chain = &["chain_id"];
+        Self {
+            token_balance: register(&registry, GaugeVec::new(opts("token_balance", "Token balance held by the solver"), chain)),
+            native_balance: register(&registry, GaugeVec::new(opts("native_balance", "Native balance held by the solver, in wei"), chain)),
+            head_block: register(&registry, IntGaugeVec::new(opts("head_block", "Latest block seen on the chain"), chain)),
+            active_auctions: register(&registry, IntGaugeVec::new(opts("active_auctions", "Auctions running for fills on the chain"), chain)),
+            unfulfilled_requests: register(
+                &registry,
+                IntGaugeVec::new(opts("unfulfilled_requests", "Swap requests from the chain awaiting a solver"), chain),
+            ),
+            trades_attempted: register(&registry, IntCounterVec::new(opts("trades_attempted_total", "Trades submitted to the chain"), chain)),
+            trades_succeeded: register(&registry, IntCounterVec::new(opts("trades_succeeded_total", "Trades mined successfully on the chain"), chain)),
+            trades_failed: register(
+                &registry,
+                IntCounterVec::new(opts("trades_failed_total", "Trades that failed on the chain, by reason"), &["chain_id", "reason"]),
+            ),
+            gas_spent: register(&registry, CounterVec::new(opts("gas_spent_wei_total", "Gas spent on trades on the chain, in wei"), chain)),
+            drand_fetch_failures: register(&registry, IntCounter::with_opts(opts("drand_fetch_failures_total", "Failed fetches of drand randomness"))),
+            skipped_requests: register(
+                &registry,
+                IntGaugeVec::new(opts("skipped_requests", "Requests not filled in the latest round, by destination chain and reason"), &["chain_id", "reason"]),
+            ),
+            skipped_volume: register(
+                &registry,
+                GaugeVec::new(opts("skipped_volume", "Token volume not filled in the latest round, by destination chain and reason"), &["chain_id", "reason"]),
+            ),
+            treasury_transfers: register(
+                &registry,
+                IntCounterVec::new(opts("treasury_transfers_total", "Sweeps to and top-ups from the treasury of the chain"), &["chain_id", "direction"]),
+            ),
+            treasury_volume: register(
+                &registry,
+                CounterVec::new(opts("treasury_volume_total", "Tokens swept to or topped up from the treasury of the chain"), &["chain_id", "direction"]),
+            ),
+            rebalances: register(
+                &registry,
+                IntCounterVec::new(opts("rebalances_total", "Inventory moves sent between chains"), &["src_chain_id", "dst_chain_id"]),
+            ),
+            rebalance_volume: register(
+                &registry,
+                CounterVec::new(opts("rebalance_volume_total", "Tokens sent between chains to rebalance inventory"), &["src_chain_id", "dst_chain_id"]),
+            ),
+            registry,
+        }
+    }
+
+    pub fn observe_chain(&self, chain_id: u64, state: &ChainState) {
+        let chain_id = chain_id.to_string();
+        self.token_balance.with_label_values(&[&chain_id]).set(f64::from(state.token_balance));
+        self.native_balance.with_label_values(&[&chain_id]).set(f64::from(state.native_balance));
+        self.head_block.with_label_values(&[&chain_id]).set(state.block_number as i64);
+        self.active_auctions.with_label_values(&[&chain_id]).set(state.active_auctions.len() as i64);
+        self.unfulfilled_requests.with_label_values(&[&chain_id]).set(state.transfers.len() as i64);
+    }
+
+    // Drops the gauges of a chain that is no longer configured; its counters keep their totals
+    pub fn forget_chain(&self, chain_id: u64) {
+        let chain_id = chain_id.to_string();
+        for gauge in [&self.token_balance, &self.native_balance] {
+            let _ = gauge.remove_label_values(&[&chain_id]);
+        }
+        for gauge in [&self.head_block, &self.active_auctions, &self.unfulfilled_requests] {
+            let _ = gauge.remove_label_values(&[&chain_id]);
+        }
+    }
+
+    pub fn trade_attempted(&self, chain_id: u64) {
+        self.trades_attempted.with_label_values(&[&chain_id.to_string()]).inc();
+    }
+
+    pub fn trade_succeeded(&self, chain_id: u64) {
+        self.trades_succeeded.with_label_values(&[&chain_id.to_string()]).inc();
+    }
+
+    pub fn trade_failed(&self, chain_id: u64, reason: &str) {
+        self.trades_failed.with_label_values(&[&chain_id.to_string(), reason]).inc();
+    }
+
+    pub fn gas_spent(&self, chain_id: u64, wei: U256) {
+        self.gas_spent.with_label_values(&[&chain_id.to_string()]).inc_by(f64::from(wei));
+    }
+
+    pub fn treasury_transfer(&self, chain_id: u64, direction: &str, amount: U256) {
+        let chain_id = chain_id.to_string();
+        self.treasury_transfers.with_label_values(&[&chain_id, direction]).inc();
+        self.treasury_volume.with_label_values(&[&chain_id, direction]).inc_by(f64::from(amount));
+    }
+
+    pub fn rebalance_sent(&self, src_chain_id: u64, dst_chain_id: u64, amount: U256) {
+        let labels = [src_chain_id.to_string(), dst_chain_id.to_string()];
+        self.rebalances.with_label_values(&labels).inc();
+        self.rebalance_volume.with_label_values(&labels).inc_by(f64::from(amount));
+    }
+
+    pub fn drand_fetch_failed(&self) {
+        self.drand_fetch_failures.inc();
+    }
+
+    // Replaces the previous round's skips, so that reasons which no longer apply drop out
+    pub fn observe_skips(&self, report: &SkipReport) {
+        self.skipped_requests.reset();
+        self.skipped_volume.reset();
+        for (chain_id, chain) in &report.by_chain {
+            let chain_id = chain_id.to_string();
+            for (reason, totals) in &chain.by_reason {
+                self.skipped_requests.with_label_values(&[&chain_id, reason.as_str()]).set(totals.requests as i64);
+                self.skipped_volume.with_label_values(&[&chain_id, reason.as_str()]).set(f64::from(totals.volume));
+            }
+        }
+    }
+
+    // Renders every metric in the Prometheus text exposition format
+    pub fn render(&self) -> eyre::Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn opts(name: &str, help: &str) -> Opts {
+    Opts::new(name, help).namespace("solver")
+}
+
+fn register<C: Collector + Clone + 'static>(registry: &Registry, collector: prometheus::Result<C>) -> C {
+    let collector = collector.expect("metric definitions are valid");
+    registry.register(Box::new(collector.clone())).expect("metrics are only registered once");
+    collector
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::metrics::Metrics;
+    use crate::model::{AuctionClock, ChainState};
+    use alloy::primitives::{Address, U256};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn metrics_are_rendered_per_chain() {
+        // given
+        let metrics = Metrics::new();
+        let state = ChainState {
+            token_addr: Address::ZERO,
+            own_addr: Address::ZERO,
+            native_balance: U256::from(7),
+            token_balance: U256::from(1000),
+            transfers: vec![],
+            already_fulfilled: vec![],
+            active_auctions: HashMap::new(),
+            block_number: 42,
+            block_timestamp: 0,
+            auction_clock: AuctionClock::Timestamp,
+        };
+
+        // when
+        metrics.observe_chain(43113, &state);
+        metrics.trade_failed(43113, "reverted");
+        metrics.drand_fetch_failed();
+        let rendered = metrics.render().unwrap();
+
+        // then
+        assert_that!(rendered).contains(r#"solver_token_balance{chain_id="43113"} 1000"#);
+        assert_that!(rendered).contains(r#"solver_head_block{chain_id="43113"} 42"#);
+        assert_that!(rendered).contains(r#"solver_trades_failed_total{chain_id="43113",reason="reverted"} 1"#);
+        assert_that!(rendered).contains("solver_drand_fetch_failures_total 1");
+    }
+}
@@ -1,80 +1,207 @@
 use crate::eth::IRouter::SwapRequestParameters;
-use alloy::primitives::{Address, U256};
-use std::time::{SystemTime, UNIX_EPOCH};
+use alloy::primitives::{Address, U256, U512};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::trace;
 
 pub type RequestId = [u8; 32];
 
+/// The unit an auction's start and end are measured in. Configured per network, and an auction
+/// always reads the clock of the chain its request originated on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuctionClock {
+    #[default]
+    Timestamp,
+    BlockNumber,
+}
+
+impl AuctionClock {
+    /// Current reading of the clock for a chain whose head is at `block_number`, mined at `block_timestamp`.
+    /// Using on-chain time rather than the local clock keeps every solver on the same price curve.
+    pub fn now(&self, block_number: u64, block_timestamp: u64) -> u64 {
+        match self {
+            AuctionClock::Timestamp => block_timestamp,
+            AuctionClock::BlockNumber => block_number,
+        }
+    }
+
+    /// Reading of the clock when the transfer was requested on its source chain, if known
+    pub fn requested_at(&self, transfer: &Transfer) -> Option<u64> {
+        match self {
+            AuctionClock::Timestamp => Some(transfer.params.requestedAt.saturating_to()),
+            AuctionClock::BlockNumber => transfer.requested_block,
+        }
+    }
+}
+
+/// Basis points representing the whole of an auction's duration or of its fee range
+pub const FULL_BPS: u64 = 10_000;
+
+/// How an auction's price falls from `start_fee` to `reserve_fee` over its duration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DecayCurve {
+    #[default]
+    Linear,
+    /// Halves the remaining fee drop every `half_life` ticks, so most price discovery happens early
+    Exponential { half_life: u64 },
+    /// Drops in equal steps every `step` ticks (e.g. every block, or every few blocks)
+    Stepwise { step: u64 },
+    /// Linear between breakpoints; `(0, 0)` and `(FULL_BPS, FULL_BPS)` are implied
+    PiecewiseLinear { breakpoints: Vec<Breakpoint> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Breakpoint {
+    pub elapsed_bps: u64, // share of the auction's duration that has passed
+    pub drop_bps: u64,    // share of the fee range the price has dropped by at that point
+}
+
+impl DecayCurve {
+    pub fn validate(&self) -> eyre::Result<()> {
+        match self {
+            DecayCurve::Linear => Ok(()),
+            DecayCurve::Exponential { half_life: 0 } => eyre::bail!("exponential decay needs a half_life greater than 0"),
+            DecayCurve::Stepwise { step: 0 } => eyre::bail!("stepwise decay needs a step greater than 0"),
+            DecayCurve::Exponential { .. } | DecayCurve::Stepwise { .. } => Ok(()),
+            DecayCurve::PiecewiseLinear { breakpoints } => {
+                let mut previous = Breakpoint { elapsed_bps: 0, drop_bps: 0 };
+                for point in breakpoints {
+                    if point.elapsed_bps > FULL_BPS || point.drop_bps > FULL_BPS {
+                        eyre::bail!("breakpoint {:?} is outside 0..={} basis points", point, FULL_BPS);
+                    }
+                    if point.elapsed_bps < previous.elapsed_bps || point.drop_bps < previous.drop_bps {
+                        eyre::bail!("breakpoint {:?} comes before {:?}; breakpoints must be increasing", point, previous);
+                    }
+                    previous = *point;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Share of the fee range, in basis points, that the price has dropped by after `elapsed` of `duration` ticks
+    pub fn drop_bps(&self, elapsed: u64, duration: u64) -> u64 {
+        if duration == 0 || elapsed >= duration {
+            return FULL_BPS;
+        }
+
+        match self {
+            DecayCurve::Linear => linear_bps(elapsed, duration),
+            DecayCurve::Exponential { half_life } => {
+                let half_life = (*half_life).max(1);
+                let halvings = elapsed / half_life;
+                if halvings >= 64 {
+                    return FULL_BPS;
+                }
+                // interpolate linearly within the current half-life so the price moves every tick
+                let remaining = FULL_BPS >> halvings;
+                let within = (remaining / 2) as u128 * (elapsed % half_life) as u128 / half_life as u128;
+                FULL_BPS - (remaining - within as u64)
+            }
+            DecayCurve::Stepwise { step } => {
+                let step = (*step).max(1);
+                linear_bps(elapsed - elapsed % step, duration)
+            }
+            DecayCurve::PiecewiseLinear { breakpoints } => {
+                let position = linear_bps(elapsed, duration);
+                let mut from = Breakpoint { elapsed_bps: 0, drop_bps: 0 };
+                let end = Breakpoint {
+                    elapsed_bps: FULL_BPS,
+                    drop_bps: FULL_BPS,
+                };
+                for to in breakpoints.iter().chain(std::iter::once(&end)) {
+                    if position <= to.elapsed_bps {
+                        let span = to.elapsed_bps - from.elapsed_bps;
+                        if span == 0 {
+                            return to.drop_bps;
+                        }
+                        return from.drop_bps + (to.drop_bps - from.drop_bps) * (position - from.elapsed_bps) / span;
+                    }
+                    from = *to;
+                }
+                FULL_BPS
+            }
+        }
+    }
+}
+
+fn linear_bps(elapsed: u64, duration: u64) -> u64 {
+    (elapsed as u128 * FULL_BPS as u128 / duration as u128) as u64
+}
+
+/// `value * numerator / denominator` with a 512-bit intermediate, so it can't overflow for any fee or token amount.
+/// Saturates at `U256::MAX` and returns zero rather than dividing by zero.
+pub fn mul_div(value: U256, numerator: U256, denominator: U256) -> U256 {
+    if denominator.is_zero() {
+        return U256::ZERO;
+    }
+    let product = U512::from(value) * U512::from(numerator);
+    (product / U512::from(denominator)).saturating_to()
+}
+
+/// `value` scaled by `bps` basis points
+pub fn apply_bps(value: U256, bps: u64) -> U256 {
+    mul_div(value, U256::from(bps), U256::from(FULL_BPS))
+}
+
 #[derive(Debug, Clone)]
 pub struct DutchAuction {
-    pub start_time: u64,
+    pub clock: AuctionClock,
+    pub curve: DecayCurve,
+    pub start_time: u64, // seconds or block number, depending on `clock`
     pub end_time: u64,
+    pub amount: U256,
+    pub slippage: U256, // the request's solverFee the fees were priced from
     pub start_fee: U256,
     pub reserve_fee: U256,
     pub current_fee: U256,
 }
 
 impl DutchAuction {
-    pub fn new(base_fee: U256, duration_seconds: u64, multiplier: f64) -> Self {
-        let start_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        let start_fee = U256::from((base_fee.to::<u64>() as f64 * multiplier) as u64);
-        
-        Self {
-            start_time,
-            end_time: start_time + duration_seconds,
-            start_fee,
-            reserve_fee: base_fee,
-            current_fee: start_fee,
-        }
-    }
+    // Slippage-based auction starting at `start`, lasting `duration` ticks of `clock` and decaying along `curve`
+    pub fn new_slippage_based(amount: U256, slippage: U256, clock: AuctionClock, curve: DecayCurve, start: u64, duration: u64) -> Self {
+        let (start_fee, min_allowed_cost) = Self::slippage_based_fees(amount, slippage);
 
-    // New constructor for slippage-based auctions
-    pub fn new_slippage_based(amount: U256, slippage: U256, expected_blocks: u64) -> Self {
-        let start_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        println!("Initial amount is: {}", amount);
-        // Calculate minAllowedCost = (1 - slippage) * amount
-        // Assuming slippage is in basis points (e.g., 100 = 1%)
-        let slippage_bps = U256::from(10000); // 100%
-        let min_allowed_cost = amount * (slippage_bps - slippage) / slippage_bps;
-        
-        // Start price = 3 * minAllowedCost
-        let start_fee = min_allowed_cost * U256::from(3);
-        
         Self {
-            start_time,
-            end_time: start_time + expected_blocks, // Using blocks instead of seconds
+            clock,
+            curve,
+            start_time: start,
+            end_time: start.saturating_add(duration),
+            amount,
+            slippage,
             start_fee,
             reserve_fee: min_allowed_cost, // Reserve price is minAllowedCost
             current_fee: start_fee,
         }
     }
 
-    pub fn update_current_fee(&mut self) -> U256 {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        
-        if now >= self.end_time {
-            self.current_fee = self.reserve_fee;
-            return self.current_fee;
-        }
-
-        if now <= self.start_time {
-            self.current_fee = self.start_fee;
-            return self.current_fee;
-        }
+    // Re-prices the auction after the user changed the request's solverFee, keeping its timeline so that
+    // every solver still agrees on the price
+    pub fn reprice(&mut self, slippage: U256) {
+        let (start_fee, reserve_fee) = Self::slippage_based_fees(self.amount, slippage);
+        self.slippage = slippage;
+        self.start_fee = start_fee;
+        self.reserve_fee = reserve_fee;
+        self.current_fee = self.current_fee.clamp(reserve_fee, start_fee);
+    }
 
-        // Linear decay from start_fee to reserve_fee over time (deterministic)
-        let elapsed = U256::from(now - self.start_time);
-        let total_duration = U256::from(self.end_time - self.start_time);
-        let fee_drop = self.start_fee - self.reserve_fee;
+    // Returns (start fee, reserve fee) for an amount and slippage
+    fn slippage_based_fees(amount: U256, slippage: U256) -> (U256, U256) {
+        // Calculate minAllowedCost = (1 - slippage) * amount
+        // Assuming slippage is in basis points (e.g., 100 = 1%), capped at 100%
+        let slippage_bps = slippage.saturating_to::<u64>().min(FULL_BPS);
+        let min_allowed_cost = apply_bps(amount, FULL_BPS - slippage_bps);
 
-        self.current_fee = self.start_fee - (fee_drop * elapsed / total_duration);
-        self.current_fee
+        // Start price = 3 * minAllowedCost
+        let start_fee = min_allowed_cost.saturating_mul(U256::from(3));
+        (start_fee, min_allowed_cost)
     }
 
-    // New method that accepts randomness as a parameter (for drand integration)
-    pub fn update_current_fee_with_randomness(&mut self, randomness: f64) -> U256 {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        
+    // Decay along `self.curve` with randomness applied (for drand integration); `now` is a reading of `self.clock`
+    // and `randomness_bps` a random value in [0, FULL_BPS)
+    pub fn update_current_fee_with_randomness(&mut self, now: u64, randomness_bps: u64) -> U256 {
         if now >= self.end_time {
             self.current_fee = self.reserve_fee;
             return self.current_fee;
@@ -85,74 +212,37 @@ impl DutchAuction {
             return self.current_fee;
         }
 
-        // Linear decay with randomness applied
-        let elapsed = U256::from(now - self.start_time);
-        let total_duration = U256::from(self.end_time - self.start_time);
-        let base_fee_drop = self.start_fee - self.reserve_fee;
+        let progress_bps = self.curve.drop_bps(now - self.start_time, self.end_time - self.start_time);
+        let base_fee_drop = self.start_fee.saturating_sub(self.reserve_fee);
 
-        // Apply randomness: scale by 0 to 2x based on normalized random value [0,1)
-        let rnd_scaled_u64 = ((randomness * 2.0 * 1_000_000.0).round() as u64).min(2_000_000);
-        let rnd_u256 = U256::from(rnd_scaled_u64);
+        // Apply randomness: scale by 0 to 2x based on normalized random value [0,1), never dropping below the reserve
+        let rnd_scaled_bps = randomness_bps.min(FULL_BPS) * 2;
+        let randomized_fee_drop = apply_bps(apply_bps(base_fee_drop, progress_bps), rnd_scaled_bps).min(base_fee_drop);
 
-        let randomized_fee_drop = base_fee_drop * rnd_u256 / U256::from(10_000_000u64);
-
-        self.current_fee = self.start_fee - (randomized_fee_drop * elapsed / total_duration);
-        
-        println!(" Time-based randomness: {:.6}, Scaled: {}, Fee drop: {}", 
-                randomness, rnd_scaled_u64, randomized_fee_drop);
-        
-        self.current_fee
-    }
+        self.current_fee = self.start_fee - randomized_fee_drop;
 
-    // New method for block-based price updates with custom step size
-    pub fn update_current_fee_by_blocks(&mut self, current_block: u64) -> U256 {
-        if current_block >= self.end_time {
-            self.current_fee = self.reserve_fee;
-            return self.current_fee;
-        }
+        trace!(clock = ?self.clock, randomness_bps, rnd_scaled_bps, fee_drop = %randomized_fee_drop, "applied randomness to fee drop");
 
-        let start_block = self.start_time; // Repurposing start_time as start_block
-        if current_block <= start_block {
-            self.current_fee = self.start_fee;
-            return self.current_fee;
-        }
-
-        // Calculate price decrease per block
-        // Price decreases by (2 * minAllowedCost / expected_blocks) per block
-        let min_allowed_cost = self.reserve_fee;
-        let total_blocks = self.end_time - start_block;
-        let price_decrease_per_block = (min_allowed_cost * U256::from(2)) / U256::from(total_blocks);
-        
-        let blocks_elapsed = current_block - start_block;
-        let total_decrease = price_decrease_per_block * U256::from(blocks_elapsed);
-        
-        if total_decrease >= self.start_fee - self.reserve_fee {
-            self.current_fee = self.reserve_fee;
-        } else {
-            self.current_fee = self.start_fee - total_decrease;
-        }
-        
         self.current_fee
     }
 
-    pub fn is_expired(&self) -> bool {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    pub fn is_expired(&self, now: u64) -> bool {
         now >= self.end_time
     }
-
-    pub fn is_profitable(&self, min_profit_threshold: U256) -> bool {
-        self.current_fee >= min_profit_threshold
-    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ChainState {
     pub token_addr: Address, // this is kinda yuck, but simplest way to support it for now
+    pub own_addr: Address,   // our signer on this chain
     pub native_balance: U256,
     pub token_balance: U256,
     pub transfers: Vec<Transfer>,
     pub already_fulfilled: Vec<RequestId>,
     pub active_auctions: HashMap<RequestId, DutchAuction>, // Add this field
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub auction_clock: AuctionClock, // clock used for auctions of transfers requested on this chain
 }
 
 // Add auction field to Transfer and required derives
@@ -160,6 +250,8 @@ pub struct ChainState {
 pub struct Transfer {
     pub request_id: RequestId,
     pub params: SwapRequestParameters,
+    pub requested_block: Option<u64>, // block of the `SwapRequested` event, only looked up for block-clocked chains
+    pub rejected: Option<SkipReason>, // set if the source router's account of the request doesn't add up
 }
 
 // Add auction_price field to Trade
@@ -188,8 +280,292 @@ impl From<&Transfer> for Trade {
     }
 }
 
+// Why the solver didn't fill a transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    InFlight,           // we've already sent a fill for it
+    UnknownDestination, // its destination chain isn't one we're configured for
+    AlreadyExecuted,
+    AlreadyFulfilled,
+    NoNativeBalance, // nothing to pay gas with on the destination chain
+    InsufficientTokens,
+    SlippageTooHigh, // solverFee above FULL_BPS
+    TokenMismatch,   // it wants a token we don't hold on the destination chain
+    PriceTooHigh,    // the auction hasn't reached our threshold yet
+    Paused,
+    Draining,
+    ChainPaused,
+    RequestSkipped, // force-skipped by an operator
+    TradeTooLarge,      // above the per-trade limit
+    ChainExposureLimit, // would take what its source chain owes us in refunds past the limit
+    TotalExposureLimit, // would take what all chains owe us in refunds past the limit
+    TradeRateLimit,     // we've made as many fills as we may in the last minute
+    CircuitOpen,        // the circuit breaker has halted trading on its source or destination chain
+    DeniedAddress,      // its sender or recipient is on the deny-list
+    NotAllowListed,     // there's an allow-list and its sender isn't on it
+    WrongSourceChain,      // it claims to come from a chain other than the one we read it from
+    RequestIdMismatch,     // its parameters don't hash to its request id
+    UnmappedToken,         // the source router doesn't map its token to the one it wants on the destination
+    DestinationNotAllowed, // the source router doesn't permit swaps to its destination chain
+    UnknownRequestTime,    // we don't know yet when it was requested, so can't price its auction
+    RouterCheckFailed,     // the source router failed to answer when we checked it, so we'll check again
+    OwnRequest,            // we made it to move our own inventory, so filling it would only send our tokens back to us
+}
+
+impl SkipReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::InFlight => "in_flight",
+            SkipReason::UnknownDestination => "unknown_destination",
+            SkipReason::AlreadyExecuted => "already_executed",
+            SkipReason::AlreadyFulfilled => "already_fulfilled",
+            SkipReason::NoNativeBalance => "no_native_balance",
+            SkipReason::InsufficientTokens => "insufficient_tokens",
+            SkipReason::SlippageTooHigh => "slippage_too_high",
+            SkipReason::TokenMismatch => "token_mismatch",
+            SkipReason::PriceTooHigh => "price_too_high",
+            SkipReason::Paused => "paused",
+            SkipReason::Draining => "draining",
+            SkipReason::ChainPaused => "chain_paused",
+            SkipReason::RequestSkipped => "request_skipped",
+            SkipReason::TradeTooLarge => "trade_too_large",
+            SkipReason::ChainExposureLimit => "chain_exposure_limit",
+            SkipReason::TotalExposureLimit => "total_exposure_limit",
+            SkipReason::TradeRateLimit => "trade_rate_limit",
+            SkipReason::CircuitOpen => "circuit_open",
+            SkipReason::DeniedAddress => "denied_address",
+            SkipReason::NotAllowListed => "not_allow_listed",
+            SkipReason::WrongSourceChain => "wrong_source_chain",
+            SkipReason::RequestIdMismatch => "request_id_mismatch",
+            SkipReason::UnmappedToken => "unmapped_token",
+            SkipReason::DestinationNotAllowed => "destination_not_allowed",
+            SkipReason::UnknownRequestTime => "unknown_request_time",
+            SkipReason::RouterCheckFailed => "router_check_failed",
+            SkipReason::OwnRequest => "own_request",
+        }
+    }
+}
+
+impl std::fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub(crate) struct BlockEvent {
     pub chain_id: u64,
     pub block_number: u64,
-}
\ No newline at end of file
+    pub timestamp: u64,
+}
+#[cfg(test)]
+mod tests {
+    use crate::model::{AuctionClock, Breakpoint, DecayCurve, DutchAuction, FULL_BPS, mul_div};
+    use alloy::primitives::U256;
+    use proptest::prelude::*;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+
+    const DURATION: u64 = 120;
+
+    fn curves() -> Vec<DecayCurve> {
+        vec![
+            DecayCurve::Linear,
+            DecayCurve::Exponential { half_life: 20 },
+            DecayCurve::Stepwise { step: 1 },
+            DecayCurve::Stepwise { step: 7 },
+            DecayCurve::PiecewiseLinear {
+                breakpoints: vec![
+                    Breakpoint {
+                        elapsed_bps: 1000,
+                        drop_bps: 5000,
+                    },
+                    Breakpoint {
+                        elapsed_bps: 1000,
+                        drop_bps: 6000,
+                    },
+                    Breakpoint {
+                        elapsed_bps: 9000,
+                        drop_bps: 9000,
+                    },
+                ],
+            },
+        ]
+    }
+
+    #[test]
+    fn curves_are_monotonic_from_nothing_to_everything() {
+        for curve in curves() {
+            assert_that!(curve.validate()).is_ok();
+            assert_that!(curve.drop_bps(0, DURATION)).is_equal_to(0);
+            assert_that!(curve.drop_bps(DURATION, DURATION)).is_equal_to(FULL_BPS);
+
+            let drops: Vec<u64> = (0..=DURATION).map(|elapsed| curve.drop_bps(elapsed, DURATION)).collect();
+            for pair in drops.windows(2) {
+                assert!(pair[0] <= pair[1], "{:?} increased the price: {:?}", curve, pair);
+                assert!(pair[1] <= FULL_BPS, "{:?} dropped past the reserve: {:?}", curve, pair);
+            }
+        }
+    }
+
+    #[test]
+    fn auction_prices_stay_between_start_and_reserve_fee() {
+        for curve in curves() {
+            let mut auction = DutchAuction::new_slippage_based(U256::from(1_000_000), U256::from(100), AuctionClock::BlockNumber, curve.clone(), 1000, DURATION);
+            let mut previous = auction.start_fee;
+
+            for now in 990..1130 {
+                let price = auction.update_current_fee_with_randomness(now, FULL_BPS / 2);
+                assert!(price <= previous, "{:?} increased the price at {}", curve, now);
+                assert!(price >= auction.reserve_fee && price <= auction.start_fee, "{:?} out of bounds at {}", curve, now);
+                previous = price;
+            }
+            assert_that!(previous).is_equal_to(auction.reserve_fee);
+        }
+    }
+
+    #[test]
+    fn exponential_decay_drops_faster_than_linear_early_on() {
+        let exponential = DecayCurve::Exponential { half_life: 20 };
+
+        assert_that!(exponential.drop_bps(20, DURATION)).is_equal_to(5000);
+        assert_that!(exponential.drop_bps(10, DURATION)).is_greater_than(DecayCurve::Linear.drop_bps(10, DURATION));
+    }
+
+    #[test]
+    fn exponential_decay_handles_the_longest_half_lives() {
+        let exponential = DecayCurve::Exponential { half_life: u64::MAX };
+
+        assert_that!(exponential.drop_bps(u64::MAX - 1, u64::MAX)).is_equal_to(4999);
+    }
+
+    #[test]
+    fn stepwise_decay_only_moves_on_step_boundaries() {
+        let stepwise = DecayCurve::Stepwise { step: 10 };
+
+        assert_that!(stepwise.drop_bps(9, DURATION)).is_equal_to(0);
+        assert_that!(stepwise.drop_bps(10, DURATION)).is_equal_to(stepwise.drop_bps(19, DURATION));
+        assert_that!(stepwise.drop_bps(20, DURATION)).is_greater_than(stepwise.drop_bps(19, DURATION));
+    }
+
+    #[test]
+    fn piecewise_linear_decay_passes_through_its_breakpoints() {
+        let piecewise = DecayCurve::PiecewiseLinear {
+            breakpoints: vec![Breakpoint {
+                elapsed_bps: 2500,
+                drop_bps: 7500,
+            }],
+        };
+
+        assert_that!(piecewise.drop_bps(30, DURATION)).is_equal_to(7500);
+        assert_that!(piecewise.drop_bps(15, DURATION)).is_equal_to(3750);
+        assert_that!(piecewise.drop_bps(75, DURATION)).is_equal_to(8750);
+    }
+
+    #[test]
+    fn invalid_curves_are_rejected() {
+        let decreasing = DecayCurve::PiecewiseLinear {
+            breakpoints: vec![
+                Breakpoint {
+                    elapsed_bps: 5000,
+                    drop_bps: 5000,
+                },
+                Breakpoint {
+                    elapsed_bps: 6000,
+                    drop_bps: 4000,
+                },
+            ],
+        };
+        let out_of_range = DecayCurve::PiecewiseLinear {
+            breakpoints: vec![Breakpoint {
+                elapsed_bps: 5000,
+                drop_bps: 10_001,
+            }],
+        };
+
+        assert_that!(decreasing.validate()).is_err();
+        assert_that!(out_of_range.validate()).is_err();
+        assert_that!(DecayCurve::Exponential { half_life: 0 }.validate()).is_err();
+        assert_that!(DecayCurve::Stepwise { step: 0 }.validate()).is_err();
+    }
+
+    fn any_u256() -> impl Strategy<Value = U256> {
+        prop_oneof![
+            Just(U256::ZERO),
+            Just(U256::MAX),
+            any::<u64>().prop_map(U256::from),
+            any::<[u64; 4]>().prop_map(U256::from_limbs),
+        ]
+    }
+
+    fn any_curve() -> impl Strategy<Value = DecayCurve> {
+        prop_oneof![
+            Just(DecayCurve::Linear),
+            (1..1000u64).prop_map(|half_life| DecayCurve::Exponential { half_life }),
+            (1..1000u64).prop_map(|step| DecayCurve::Stepwise { step }),
+            Just(curves()[4].clone()),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn auctions_of_any_size_price_between_start_and_reserve_fee(
+            amount in any_u256(),
+            slippage in 0..=FULL_BPS,
+            curve in any_curve(),
+            start in any::<u64>(),
+            duration in prop_oneof![Just(0u64), Just(u64::MAX), any::<u64>()],
+            offset in any::<u64>(),
+            randomness in 0..FULL_BPS,
+        ) {
+            let mut auction = DutchAuction::new_slippage_based(amount, U256::from(slippage), AuctionClock::Timestamp, curve, start, duration);
+            let price = auction.update_current_fee_with_randomness(start.saturating_add(offset), randomness);
+
+            prop_assert!(auction.reserve_fee <= auction.start_fee);
+            prop_assert!(price >= auction.reserve_fee && price <= auction.start_fee);
+            prop_assert!(auction.reserve_fee <= amount);
+        }
+
+        #[test]
+        fn zero_duration_auctions_go_straight_to_the_reserve_fee(amount in any_u256(), start in 1..u64::MAX, randomness in 0..FULL_BPS) {
+            let mut auction = DutchAuction::new_slippage_based(amount, U256::from(100), AuctionClock::BlockNumber, DecayCurve::Linear, start, 0);
+
+            prop_assert!(auction.is_expired(start));
+            prop_assert_eq!(auction.update_current_fee_with_randomness(start, randomness), auction.reserve_fee);
+        }
+
+        #[test]
+        fn full_slippage_auctions_cost_nothing(amount in any_u256(), now in any::<u64>(), randomness in 0..FULL_BPS) {
+            let mut auction = DutchAuction::new_slippage_based(amount, U256::from(FULL_BPS), AuctionClock::Timestamp, DecayCurve::Linear, 0, 120);
+
+            prop_assert_eq!(auction.reserve_fee, U256::ZERO);
+            prop_assert_eq!(auction.update_current_fee_with_randomness(now, randomness), U256::ZERO);
+        }
+
+        #[test]
+        fn slippage_above_100_percent_is_capped(amount in any_u256(), slippage in any_u256()) {
+            let auction = DutchAuction::new_slippage_based(amount, slippage, AuctionClock::Timestamp, DecayCurve::Linear, 0, 120);
+
+            prop_assert!(auction.reserve_fee <= amount);
+        }
+
+        #[test]
+        fn mul_div_is_exact_for_huge_values(value in any_u256(), denominator in any_u256()) {
+            prop_assume!(!denominator.is_zero());
+
+            prop_assert_eq!(mul_div(value, denominator, denominator), value);
+            prop_assert_eq!(mul_div(value, U256::ZERO, denominator), U256::ZERO);
+        }
+    }
+
+    #[test]
+    fn huge_amounts_keep_their_precision() {
+        let amount = U256::MAX;
+        let auction = DutchAuction::new_slippage_based(amount, U256::from(5000), AuctionClock::Timestamp, DecayCurve::Linear, 0, 120);
+
+        assert_that!(auction.reserve_fee).is_equal_to(amount / U256::from(2));
+        assert_that!(auction.start_fee).is_equal_to(U256::MAX);
+        assert_that!(mul_div(U256::MAX, U256::from(1), U256::ZERO)).is_equal_to(U256::ZERO);
+    }
+}
@@ -2,12 +2,16 @@ use crate::config::NetworkConfig;
 use crate::eth::ERC20FaucetToken;
 use crate::eth::ERC20FaucetToken::ERC20FaucetTokenInstance;
 use crate::eth::Router::RouterInstance;
-use crate::model::{BlockEvent, ChainState, Transfer};
+use crate::eth::IRouter::SwapRequestParameters;
+use crate::model::{AuctionClock, BlockEvent, ChainState, RequestId, Transfer};
+use crate::request_blocks::{BlockHistory, RequestBlocks};
+use crate::request_checks::{RequestChecks, SwapRouter};
+use crate::signer::{Signers, SolverSigner};
+use crate::treasury::Treasury;
 use crate::solver::ChainStateProvider;
-use alloy::network::EthereumWallet;
-use alloy::primitives::{Address, U256};
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{Address, B256, U256};
 use alloy::providers::{DynProvider, Provider, ProviderBuilder, WsConnect};
-use alloy::signers::local::PrivateKeySigner;
 use async_trait::async_trait;
 use futures::Stream;
 use futures::StreamExt;
@@ -16,6 +20,10 @@ use itertools::Itertools;
 use std::collections::HashMap;
 use std::pin::Pin;
 use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+pub(crate) type BlockStream = Pin<Box<dyn Stream<Item = BlockEvent> + Send>>;
 
 pub(crate) struct Network<P> {
     pub chain_id: u64,
@@ -23,105 +31,180 @@ pub(crate) struct Network<P> {
     pub own_addr: Address,
     pub token: ERC20FaucetTokenInstance<P>,
     pub router: RouterInstance<P>,
+    pub auction_clock: AuctionClock,
+    pub treasury: Option<Treasury>,
+    request_blocks: RequestBlocks, // block each request was made in, for block-clocked auctions
+    request_checks: RequestChecks, // outcome of checking each request against the router
 }
 
 impl Network<DynProvider> {
-    pub async fn create_many(private_key: &str, network_configs: &[NetworkConfig]) -> eyre::Result<HashMap<u64, Self>> {
+    pub async fn create_many(signers: &Signers, network_configs: &[NetworkConfig]) -> eyre::Result<HashMap<u64, Arc<Self>>> {
         let mut networks = HashMap::new();
-        let signer = PrivateKeySigner::from_str(private_key)?;
 
         for config in network_configs.iter() {
-            let network = Network::new(&signer, config).await?;
-            match network.withdraw_tokens().await {
-                Ok(()) => {}
-                Err(e) => {
-                    println!("failed to withdraw from faucet - probably already done: {e}")
-                }
-            };
-
-            networks.insert(config.chain_id, network);
+            let network = Network::create(&signers.for_network(config)?, config).await?;
+            networks.insert(config.chain_id, Arc::new(network));
         }
 
-        println!("{} chain(s) have been configured", network_configs.len());
+        info!("{} chain(s) have been configured", network_configs.len());
         Ok(networks)
     }
 
-    pub async fn new(signer: &PrivateKeySigner, config: &NetworkConfig) -> eyre::Result<Self> {
+    // Connects to the chain and makes sure we have tokens to trade with
+    pub async fn create(signer: &SolverSigner, config: &NetworkConfig) -> eyre::Result<Self> {
+        let network = Network::new(signer, config).await?;
+        match network.withdraw_tokens().await {
+            Ok(()) => {}
+            Err(e) => {
+                warn!(chain_id = config.chain_id, error = %e, "failed to withdraw from faucet - probably already done")
+            }
+        };
+        Ok(network)
+    }
+
+    pub async fn new(signer: &SolverSigner, config: &NetworkConfig) -> eyre::Result<Self> {
         let url = config.rpc_url.clone();
         let chain_id = config.chain_id;
         let provider = ProviderBuilder::new()
             .with_gas_estimation()
-            .wallet(EthereumWallet::new(signer.clone()))
+            .wallet(signer.clone())
             .connect_ws(WsConnect::new(url))
             .await?
             .erased();
         let own_addr = signer.address();
 
-        println!("own addr: {own_addr}");
+        info!(chain_id, %own_addr, "connected to chain");
         Ok(Self {
             token: ERC20FaucetToken::new(config.rusd_address.parse()?, provider.clone()),
             router: RouterInstance::new(config.router_address.parse()?, provider.clone()),
             chain_id,
             provider,
             own_addr,
+            auction_clock: config.auction_clock,
+            treasury: config.treasury.as_ref().map(Treasury::from_config).transpose()?,
+            request_blocks: RequestBlocks::new(),
+            request_checks: RequestChecks::new(),
         })
     }
 }
 
 impl<P: Provider> Network<P> {
     pub async fn withdraw_tokens(&self) -> eyre::Result<()> {
-        println!("checking funds for {}", self.chain_id);
+        info!(chain_id = self.chain_id, "checking funds");
 
         let min_balance = U256::from_str("1_000_000_000_000_000_000_000_000_000")?;
         let rusd_balance = self.token.balanceOf(self.own_addr).call().await?;
         if rusd_balance > min_balance {
-            println!("balance {} - not withdrawing tokens for chain_id {}", rusd_balance, &self.chain_id);
+            info!(chain_id = self.chain_id, balance = %rusd_balance, "not withdrawing tokens");
             return Ok(());
         }
 
         let tx = self.token.mint().send().await?;
         let hash = tx.watch().await?;
-        println!("withdrew tokens for chain_id {}: {}", &self.chain_id, hash);
+        info!(chain_id = self.chain_id, tx_hash = %hash, "withdrew tokens");
         Ok(())
     }
 
-    pub async fn stream_block_numbers(&self) -> eyre::Result<Pin<Box<dyn Stream<Item = BlockEvent> + Send>>> {
+    pub async fn stream_block_numbers(&self) -> eyre::Result<BlockStream> {
         let chain_id = self.chain_id;
         let stream = self.provider.subscribe_blocks().await?.into_stream().map(move |header| BlockEvent {
             chain_id,
             block_number: header.number,
+            timestamp: header.timestamp,
         });
 
         Ok(Box::pin(stream))
     }
 }
 
+#[async_trait]
+impl SwapRouter for Network<DynProvider> {
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    async fn swap_request_id(&self, params: &SwapRequestParameters) -> eyre::Result<RequestId> {
+        Ok(self.router.getSwapRequestId(params.clone()).call().await?.0)
+    }
+
+    async fn allows_destination(&self, dst_chain_id: U256) -> eyre::Result<bool> {
+        Ok(self.router.getAllowedDstChainId(dst_chain_id).call().await?)
+    }
+
+    async fn maps_token(&self, token_in: Address, dst_chain_id: U256, token_out: Address) -> eyre::Result<bool> {
+        Ok(self.router.isDstTokenMapped(token_in, dst_chain_id, token_out).call().await?)
+    }
+}
+
+#[async_trait]
+impl BlockHistory for Network<DynProvider> {
+    async fn block_timestamp(&self, block_number: u64) -> eyre::Result<u64> {
+        let block = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number))
+            .await?
+            .ok_or_else(|| eyre::eyre!("chain {} returned no block {}", self.chain_id, block_number))?;
+        Ok(block.header.timestamp)
+    }
+
+    async fn swap_requested_in(&self, request_id: &RequestId, from_block: u64, to_block: u64) -> eyre::Result<Option<u64>> {
+        let events = self
+            .router
+            .SwapRequested_filter()
+            .topic1(B256::from(*request_id))
+            .from_block(from_block)
+            .to_block(to_block)
+            .query()
+            .await?;
+        Ok(events.first().and_then(|(_, log)| log.block_number))
+    }
+}
+
 #[async_trait]
 impl ChainStateProvider for Network<DynProvider> {
     async fn fetch_state(&self) -> eyre::Result<ChainState> {
         let token_addr = *self.token.address();
+        let head = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .ok_or_else(|| eyre::eyre!("chain {} returned no latest block", self.chain_id))?;
+        // read before the balance, so that any fill listed here is already taken out of it
+        let already_fulfilled = self.router.getFulfilledTransfers().call().await?.into_iter().map_into().collect_vec();
         let native_balance = self.provider.get_balance(self.own_addr).await?;
         let token_balance = self.token.balanceOf(self.own_addr).call().await?;
-        let already_fulfilled = self.router.getFulfilledTransfers().call().await?.into_iter().map_into().collect_vec();
 
         let unfulfilled = self.router.getUnfulfilledSolverRefunds().call().await?;
         let reqs = unfulfilled.into_iter().map(async |id| -> eyre::Result<Transfer> {
             let params = self.router.getSwapRequestParameters(id).call().await?;
+            let requested_block = match self.auction_clock {
+                AuctionClock::BlockNumber => {
+                    let head = (head.header.number, head.header.timestamp);
+                    self.request_blocks.lookup(self, &id.0, params.requestedAt.saturating_to(), head).await
+                }
+                AuctionClock::Timestamp => None,
+            };
+            let rejected = self.request_checks.check(self, &id.0, &params).await;
             Ok(Transfer { 
                 request_id: *id, 
                 params,
-                // auction: None, // Will be set by solver
+                requested_block,
+                rejected,
             })
         });
         let transfers = try_join_all(reqs).await?;
 
         Ok(ChainState {
             token_addr,
+            own_addr: self.own_addr,
             native_balance,
             token_balance,
             transfers,
             already_fulfilled,
             active_auctions: HashMap::new(), // Initialize empty
+            block_number: head.header.number,
+            block_timestamp: head.header.timestamp,
+            auction_clock: self.auction_clock,
         })
     }
 }
@@ -0,0 +1,237 @@
+use crate::config::{NetworkConfig, RebalancerConfig};
+use crate::metrics::Metrics;
+use crate::eth::Router;
+use crate::model::{FULL_BPS, RequestId, mul_div};
+use crate::network::Network;
+use alloy::primitives::{B256, U256};
+use alloy::providers::DynProvider;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+// Moves tokens from one chain to another on behalf of the rebalancer. `fee` is a token amount, passed as the
+// request's `solverFee`, which the router takes on top of `amount` and pays to whoever fills it.
+// Returns the id of the swap request, which is fulfilled on `dst` once the tokens have arrived.
+#[async_trait]
+pub(crate) trait Bridge: Send + Sync {
+    async fn send(&self, src: &Network<DynProvider>, dst: &Network<DynProvider>, amount: U256, fee: U256) -> eyre::Result<RequestId>;
+}
+
+// Bridges through the OnlySwaps router itself: we request a swap to our own address on the
+// destination chain, which any solver will fill once its auction has come down far enough
+pub(crate) struct RouterBridge;
+
+// Keeps the token balance of every chain with an inventory band within it, by moving tokens from
+// chains holding more than their target to chains that have dropped below their minimum
+pub(crate) struct Rebalancer {
+    bridge: Box<dyn Bridge>,
+    pending: PendingMoves,
+}
+
+// Moves that haven't been filled yet. Neither chain of one takes part in another until it's filled or
+// has expired, as their balances don't reflect it until then.
+#[derive(Default)]
+struct PendingMoves {
+    moves: Vec<(RequestId, Move, Instant)>, // when each was sent
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Move {
+    pub src_chain_id: u64,
+    pub dst_chain_id: u64,
+    pub amount: U256,
+    pub fee: U256,
+}
+
+#[async_trait]
+impl Bridge for RouterBridge {
+    async fn send(&self, src: &Network<DynProvider>, dst: &Network<DynProvider>, amount: U256, fee: U256) -> eyre::Result<RequestId> {
+        let approval = src.token.approve(*src.router.address(), amount + fee).send().await?.get_receipt().await?;
+        if !approval.status() {
+            eyre::bail!("approving the router reverted in {}", approval.transaction_hash);
+        }
+
+        let receipt = src
+            .router
+            .requestCrossChainSwap(*src.token.address(), *dst.token.address(), amount, fee, U256::from(dst.chain_id), dst.own_addr)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        if !receipt.status() {
+            eyre::bail!("swap request reverted in {}", receipt.transaction_hash);
+        }
+        let requested = receipt
+            .decoded_log::<Router::SwapRequested>()
+            .ok_or_else(|| eyre::eyre!("swap request in {} emitted no SwapRequested event", receipt.transaction_hash))?;
+        Ok(requested.requestId.0)
+    }
+}
+
+impl Rebalancer {
+    pub fn new(bridge: Box<dyn Bridge>) -> Self {
+        Self { bridge, pending: PendingMoves::default() }
+    }
+
+    // Plans moves from the latest known `balances` and sends them, leaving out chains still waiting on a previous
+    // move, which is done once its request is among the `fulfilled` ones. Returns the moves it sent.
+    pub async fn rebalance(&mut self, config: &RebalancerConfig, networks: &HashMap<u64, Arc<Network<DynProvider>>>, configs: &[NetworkConfig], balances: &HashMap<u64, U256>, fulfilled: &HashSet<RequestId>, metrics: &Metrics) -> Vec<Move> {
+        let waiting = self.pending.waiting(fulfilled, Duration::from_secs(config.cooldown_secs), Instant::now());
+        let balances: HashMap<u64, U256> = balances.iter().filter(|(chain_id, _)| !waiting.contains(chain_id)).map(|(chain_id, balance)| (*chain_id, *balance)).collect();
+
+        let mut sent = Vec::new();
+        for planned in plan(configs, &balances, config.fee_bps) {
+            let (Some(src), Some(dst)) = (networks.get(&planned.src_chain_id), networks.get(&planned.dst_chain_id)) else {
+                continue;
+            };
+
+            match self.bridge.send(src, dst, planned.amount, planned.fee).await {
+                Ok(request_id) => {
+                    self.pending.sent(request_id, planned, Instant::now());
+                    metrics.rebalance_sent(planned.src_chain_id, planned.dst_chain_id, planned.amount);
+                    info!(src_chain_id = planned.src_chain_id, dst_chain_id = planned.dst_chain_id, amount = %planned.amount, fee = %planned.fee, request_id = %B256::from(request_id), "moved inventory between chains");
+                    sent.push(planned);
+                }
+                Err(e) => {
+                    warn!(src_chain_id = planned.src_chain_id, dst_chain_id = planned.dst_chain_id, amount = %planned.amount, error = %e, "failed to move inventory between chains");
+                }
+            }
+        }
+        sent
+    }
+}
+
+// Pairs every chain below its minimum, neediest first, with the chain that has the most to spare above
+// its target. A chain is topped up to its target, as far as the donor can afford it including the fee,
+// which is `fee_bps` of the amount moved.
+pub(crate) fn plan(configs: &[NetworkConfig], balances: &HashMap<u64, U256>, fee_bps: u64) -> Vec<Move> {
+    let mut deficits = Vec::new();
+    let mut surpluses = Vec::new();
+    for config in configs {
+        let (Some(inventory), Some(balance)) = (&config.inventory, balances.get(&config.chain_id)) else {
+            continue;
+        };
+        if *balance < inventory.min_token_balance {
+            deficits.push((config.chain_id, inventory.target_token_balance - *balance));
+        } else if *balance > inventory.target_token_balance {
+            surpluses.push((config.chain_id, *balance - inventory.target_token_balance));
+        }
+    }
+    deficits.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    let mut moves = Vec::new();
+    for (dst_chain_id, needed) in deficits {
+        let Some((src_chain_id, spare)) = surpluses.iter_mut().filter(|(_, spare)| !spare.is_zero()).max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0))) else {
+            break;
+        };
+        let amount = needed.min(mul_div(*spare, U256::from(FULL_BPS), U256::from(FULL_BPS + fee_bps)));
+        let fee = mul_div(amount, U256::from(fee_bps), U256::from(FULL_BPS));
+        if amount.is_zero() {
+            continue;
+        }
+        *spare = spare.saturating_sub(amount + fee);
+        moves.push(Move {
+            src_chain_id: *src_chain_id,
+            dst_chain_id,
+            amount,
+            fee,
+        });
+    }
+    moves
+}
+
+impl PendingMoves {
+    fn sent(&mut self, request_id: RequestId, sent: Move, now: Instant) {
+        self.moves.push((request_id, sent, now));
+    }
+
+    // Forgets the moves that have been filled or have waited longer than `expiry`, and returns the chains of the rest
+    fn waiting(&mut self, fulfilled: &HashSet<RequestId>, expiry: Duration, now: Instant) -> HashSet<u64> {
+        self.moves.retain(|(request_id, _, sent_at)| !fulfilled.contains(request_id) && now.duration_since(*sent_at) < expiry);
+        self.moves.iter().flat_map(|(_, pending, _)| [pending.src_chain_id, pending.dst_chain_id]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{InventoryConfig, NetworkConfig};
+    use crate::rebalancer::{Move, PendingMoves, plan};
+    use crate::util::test::{generate_request_id, network_config};
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::collections::{HashMap, HashSet};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn the_neediest_chain_is_topped_up_from_the_one_with_most_to_spare() {
+        // given
+        let configs = vec![network(1, 100, 500), network(2, 100, 500), network(3, 100, 500), network(4, 100, 500)];
+        let balances = HashMap::from([(1, U256::from(50)), (2, U256::from(900)), (3, U256::from(1500)), (4, U256::from(0))]);
+
+        // when
+        let moves = plan(&configs, &balances, 0);
+
+        // then
+        assert_that!(moves).is_equal_to(vec![
+            Move { src_chain_id: 3, dst_chain_id: 4, amount: U256::from(500), fee: U256::ZERO },
+            Move { src_chain_id: 3, dst_chain_id: 1, amount: U256::from(450), fee: U256::ZERO },
+        ]);
+    }
+
+    #[test]
+    fn moves_are_limited_to_what_the_donor_can_spare_including_the_fee() {
+        // given
+        let configs = vec![network(1, 100, 500), network(2, 100, 500), network(3, 0, 0)];
+        let balances = HashMap::from([(1, U256::from(0)), (2, U256::from(702)), (3, U256::from(1_000_000))]);
+        let mut unbanded = configs.clone();
+        unbanded[2].inventory = None;
+
+        // when
+        let moves = plan(&unbanded, &balances, 100);
+
+        // then
+        // 200 of the 202 to spare, and 1% of that on top
+        assert_that!(moves).is_equal_to(vec![Move { src_chain_id: 2, dst_chain_id: 1, amount: U256::from(200), fee: U256::from(2) }]);
+    }
+
+    #[test]
+    fn chains_within_their_band_are_left_alone() {
+        let configs = vec![network(1, 100, 500), network(2, 100, 500)];
+        let balances = HashMap::from([(1, U256::from(100)), (2, U256::from(5000))]);
+        assert_that!(plan(&configs, &balances, 10)).is_empty();
+    }
+
+    #[test]
+    fn chains_wait_on_a_move_until_it_is_filled_or_expires() {
+        // given
+        let (filled, expiring) = (generate_request_id(), generate_request_id());
+        let sent_at = Instant::now();
+        let mut pending = PendingMoves::default();
+        pending.sent(filled, Move { src_chain_id: 1, dst_chain_id: 2, amount: U256::from(100), fee: U256::ZERO }, sent_at);
+        pending.sent(expiring, Move { src_chain_id: 3, dst_chain_id: 4, amount: U256::from(100), fee: U256::ZERO }, sent_at);
+        let expiry = Duration::from_secs(600);
+
+        // when
+        let unfilled = pending.waiting(&HashSet::new(), expiry, sent_at + Duration::from_secs(1));
+        let one_filled = pending.waiting(&HashSet::from([filled]), expiry, sent_at + Duration::from_secs(2));
+        let expired = pending.waiting(&HashSet::new(), expiry, sent_at + expiry);
+
+        // then
+        assert_that!(unfilled).is_equal_to(HashSet::from([1, 2, 3, 4]));
+        assert_that!(one_filled).is_equal_to(HashSet::from([3, 4]));
+        assert_that!(expired).is_empty();
+    }
+
+    fn network(chain_id: u64, min: u64, target: u64) -> NetworkConfig {
+        NetworkConfig {
+            inventory: Some(InventoryConfig {
+                min_token_balance: U256::from(min),
+                target_token_balance: U256::from(target),
+            }),
+            ..network_config(chain_id)
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use crate::config::{ConfigFile, NetworkConfig, load_config_file};
+use shellexpand::tilde;
+use std::fs;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+// How often we check whether the config file has been modified
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// Reloads the config file whenever it or a file it points to is modified or we receive SIGHUP, and
+// hands every valid version to the `App` to apply. An invalid file is logged and ignored, so the solver carries on
+// with the config it has.
+pub(crate) struct ConfigReloader {
+    path: String,
+    modified: Vec<Option<SystemTime>>, // of the config file and the files it points to
+    updates: watch::Sender<ConfigFile>,
+}
+
+// What applying a new config means for the networks we're connected to
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct NetworkChanges {
+    pub connect: Vec<u64>, // new chains, and chains whose connection settings changed
+    pub disconnect: Vec<u64>,
+}
+
+impl ConfigReloader {
+    pub fn new(path: String, config: ConfigFile) -> (Self, watch::Receiver<ConfigFile>) {
+        let modified = modified_at(&watched_files(&path, &config));
+        let (updates, receiver) = watch::channel(config);
+        (Self { path, modified, updates }, receiver)
+    }
+
+    pub async fn start(mut self) -> eyre::Result<()> {
+        let mut sighup = signal(SignalKind::hangup())?;
+        let mut poll = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = sighup.recv() => {
+                    info!("received SIGHUP, reloading config");
+                    self.reload();
+                    self.modified = self.modified_now();
+                }
+                _ = poll.tick() => {
+                    if self.modified_now() != self.modified {
+                        info!("config file changed, reloading config");
+                        self.reload();
+                        self.modified = self.modified_now();
+                    }
+                }
+            }
+        }
+    }
+
+    // The files the last valid config points to are watched along with it
+    fn modified_now(&self) -> Vec<Option<SystemTime>> {
+        modified_at(&watched_files(&self.path, &self.updates.borrow()))
+    }
+
+    fn reload(&self) {
+        match load_config_file(&self.path) {
+            Ok(config) => {
+                self.updates.send_replace(config);
+            }
+            Err(e) => warn!(error = %format!("{:#}", e), "ignoring invalid config, keeping the current one"),
+        }
+    }
+}
+
+impl NetworkChanges {
+    pub fn between(current: &[NetworkConfig], next: &[NetworkConfig]) -> Self {
+        let connect = next
+            .iter()
+            .filter(|config| match current.iter().find(|existing| existing.chain_id == config.chain_id) {
+                Some(existing) => needs_reconnect(existing, config),
+                None => true,
+            })
+            .map(|config| config.chain_id)
+            .collect();
+        let disconnect = current
+            .iter()
+            .filter(|existing| !next.iter().any(|config| config.chain_id == existing.chain_id))
+            .map(|existing| existing.chain_id)
+            .collect();
+        Self { connect, disconnect }
+    }
+}
+
+// `min_native_balance` only matters to the health checks, so changing it doesn't need a new connection
+fn needs_reconnect(current: &NetworkConfig, next: &NetworkConfig) -> bool {
+    current.rpc_url != next.rpc_url
+        || current.rusd_address != next.rusd_address
+        || current.router_address != next.router_address
+        || current.auction_clock != next.auction_clock
+        || current.signer != next.signer
+        || current.treasury != next.treasury
+}
+
+fn watched_files<'a>(path: &'a str, config: &'a ConfigFile) -> Vec<&'a str> {
+    let mut files = vec![path];
+    files.extend(config.compliance.deny_list.as_deref());
+    files.extend(config.compliance.allow_list.as_deref());
+    files
+}
+
+fn modified_at(paths: &[&str]) -> Vec<Option<SystemTime>> {
+    paths.iter().map(|path| fs::metadata(tilde(path).as_ref()).and_then(|metadata| metadata.modified()).ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{ConfigFile, NetworkConfig};
+    use crate::reload::{ConfigReloader, NetworkChanges};
+    use crate::util::test::network_config;
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::fs;
+
+    #[test]
+    fn only_new_and_reconfigured_chains_are_reconnected() {
+        // given
+        let current = vec![network(1, "wss://one"), network(2, "wss://two"), network(3, "wss://three")];
+        let mut funded = network(1, "wss://one");
+        funded.min_native_balance = U256::from(100);
+
+        // when
+        let changes = NetworkChanges::between(&current, &[funded, network(2, "wss://two-backup"), network(4, "wss://four")]);
+
+        // then
+        assert_that!(changes.connect).is_equal_to(vec![2, 4]);
+        assert_that!(changes.disconnect).is_equal_to(vec![3]);
+    }
+
+    #[test]
+    fn invalid_configs_are_not_applied() {
+        // given
+        let path = std::env::temp_dir().join(format!("solver-reload-{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        fs::write(&path, r#"{"networks": [{"chain_id": 1, "rpc_url": "wss://one", "rusd_address": "0x0000000000000000000000000000000000000000", "router_address": "0x0000000000000000000000000000000000000000"}]}"#).unwrap();
+        let (reloader, mut updates) = ConfigReloader::new(path.clone(), config(vec![]));
+
+        // when
+        reloader.reload();
+        let valid = updates.has_changed().unwrap();
+        let reloaded = updates.borrow_and_update().networks.len();
+        fs::write(&path, r#"{"networks": [{"chain_id": 1, "rpc_url": "https://one"}]}"#).unwrap();
+        reloader.reload();
+        let invalid = updates.has_changed().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // then
+        assert_that!(valid).is_true();
+        assert_that!(reloaded).is_equal_to(1);
+        assert_that!(invalid).is_false();
+    }
+
+    fn network(chain_id: u64, rpc_url: &str) -> NetworkConfig {
+        NetworkConfig {
+            rpc_url: rpc_url.to_string(),
+            ..network_config(chain_id)
+        }
+    }
+
+    fn config(networks: Vec<NetworkConfig>) -> ConfigFile {
+        ConfigFile {
+            networks,
+            solver_config: None,
+            health: Default::default(),
+            rebalancer: Default::default(),
+            risk: Default::default(),
+            circuit_breaker: Default::default(),
+            compliance: Default::default(),
+            demo_mode: false,
+        }
+    }
+}
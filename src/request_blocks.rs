@@ -0,0 +1,191 @@
+use crate::model::RequestId;
+use alloy::primitives::B256;
+use async_trait::async_trait;
+use moka::sync::Cache;
+use std::time::Duration;
+use tracing::warn;
+
+// How many blocks from the first one stamped with a request's `requestedAt` we search for its event, as
+// chains with sub-second blocks stamp several blocks with the same second
+const REQUEST_BLOCK_SPAN: u64 = 100;
+
+// How long we wait before looking for the block of a request again after failing to find it
+const RETRY_AFTER: Duration = Duration::from_secs(30);
+
+// What finding the block a request was made in needs from its chain
+#[async_trait]
+pub(crate) trait BlockHistory: Sync {
+    async fn block_timestamp(&self, block_number: u64) -> eyre::Result<u64>;
+    // Block of the request's `SwapRequested` event, if it's between the two blocks (inclusive)
+    async fn swap_requested_in(&self, request_id: &RequestId, from_block: u64, to_block: u64) -> eyre::Result<Option<u64>>;
+}
+
+// The blocks requests were made in, which auctions on block-clocked chains are anchored to. A found
+// block never changes, so it's kept; a failed lookup is remembered for a while, so that it isn't
+// repeated on every block, and then tried again.
+pub(crate) struct RequestBlocks {
+    found: Cache<RequestId, u64>,
+    missed: Cache<RequestId, ()>,
+}
+
+impl RequestBlocks {
+    pub fn new() -> Self {
+        Self {
+            found: Cache::new(10_000),
+            missed: Cache::builder().max_capacity(10_000).time_to_live(RETRY_AFTER).build(),
+        }
+    }
+
+    // `head` is the number and timestamp of the latest block
+    pub async fn lookup(&self, history: &impl BlockHistory, request_id: &RequestId, requested_at: u64, head: (u64, u64)) -> Option<u64> {
+        if let Some(block_number) = self.found.get(request_id) {
+            return Some(block_number);
+        }
+        if self.missed.contains_key(request_id) {
+            return None;
+        }
+
+        match find_request_block(history, request_id, requested_at, head).await {
+            Ok(Some(block_number)) => {
+                self.found.insert(*request_id, block_number);
+                Some(block_number)
+            }
+            Ok(None) => {
+                warn!(request_id = %B256::from(*request_id), requested_at, "no SwapRequested event around the time of the request, will look again");
+                self.missed.insert(*request_id, ());
+                None
+            }
+            Err(e) => {
+                warn!(request_id = %B256::from(*request_id), error = %e, "failed to look up SwapRequested event, will look again");
+                self.missed.insert(*request_id, ());
+                None
+            }
+        }
+    }
+}
+
+// The request was made in a block stamped with its `requestedAt`. We find the first such block by stepping
+// back from the head in doubling strides until we pass it and then bisecting, so that recent requests
+// take few lookups, and only search the logs of the blocks from there.
+async fn find_request_block(history: &impl BlockHistory, request_id: &RequestId, requested_at: u64, (head_number, head_timestamp): (u64, u64)) -> eyre::Result<Option<u64>> {
+    if head_timestamp < requested_at {
+        return Ok(None);
+    }
+
+    // `end` is stamped at or after the request; every block before `start` is stamped before it
+    let mut end = head_number;
+    let mut stride = 1;
+    let mut start = 0;
+    while end > 0 {
+        let probe = end.saturating_sub(stride);
+        if history.block_timestamp(probe).await? < requested_at {
+            start = probe + 1;
+            break;
+        }
+        end = probe;
+        stride = stride.saturating_mul(2);
+    }
+    while start < end {
+        let mid = start + (end - start) / 2;
+        if history.block_timestamp(mid).await? < requested_at {
+            start = mid + 1;
+        } else {
+            end = mid;
+        }
+    }
+
+    history.swap_requested_in(request_id, end, end.saturating_add(REQUEST_BLOCK_SPAN).min(head_number)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::RequestId;
+    use crate::request_blocks::{BlockHistory, RequestBlocks};
+    use crate::util::test::generate_request_id;
+    use async_trait::async_trait;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn requests_are_found_without_scanning_the_whole_chain() {
+        // given
+        // two blocks a second, and the request in the second block of second 1_000_400
+        let request_id = generate_request_id();
+        let history = StubbedHistory::new(1_000_000, |n| 1_000_000 + n / 2, Some((request_id, 801)));
+
+        // when
+        let found = RequestBlocks::new().lookup(&history, &request_id, 1_000_400, (1_000_000, 1_500_000)).await;
+
+        // then
+        assert_that!(found).contains(801);
+        let searched = history.searched.lock().unwrap().clone();
+        assert_that!(searched).is_equal_to(vec![(800, 900)]);
+        assert_that!(*history.lookups.lock().unwrap()).is_less_than(50);
+    }
+
+    #[tokio::test]
+    async fn misses_are_remembered_rather_than_looked_up_every_block() {
+        // given
+        let request_id = generate_request_id();
+        let history = StubbedHistory::new(1000, |n| n, None);
+        let blocks = RequestBlocks::new();
+
+        // when
+        let first = blocks.lookup(&history, &request_id, 900, (1000, 1000)).await;
+        let lookups = *history.lookups.lock().unwrap();
+        let second = blocks.lookup(&history, &request_id, 900, (1001, 1001)).await;
+
+        // then
+        assert_that!(first).is_none();
+        assert_that!(second).is_none();
+        assert_that!(*history.lookups.lock().unwrap()).is_equal_to(lookups);
+        assert_that!(history.searched.lock().unwrap().len()).is_equal_to(1);
+    }
+
+    #[tokio::test]
+    async fn requests_from_the_future_are_not_searched_for() {
+        let request_id = generate_request_id();
+        let history = StubbedHistory::new(1000, |n| n, Some((request_id, 1000)));
+        assert_that!(RequestBlocks::new().lookup(&history, &request_id, 2000, (1000, 1000)).await).is_none();
+        assert_that!(*history.lookups.lock().unwrap()).is_equal_to(0);
+    }
+
+    // A chain of `head + 1` blocks stamped by `timestamp`, with at most one request in it
+    struct StubbedHistory {
+        head: u64,
+        timestamp: fn(u64) -> u64,
+        request: Option<(RequestId, u64)>,
+        lookups: Mutex<usize>,
+        searched: Mutex<Vec<(u64, u64)>>,
+    }
+
+    impl StubbedHistory {
+        fn new(head: u64, timestamp: fn(u64) -> u64, request: Option<(RequestId, u64)>) -> Self {
+            Self {
+                head,
+                timestamp,
+                request,
+                lookups: Mutex::new(0),
+                searched: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl BlockHistory for StubbedHistory {
+        async fn block_timestamp(&self, block_number: u64) -> eyre::Result<u64> {
+            assert!(block_number <= self.head, "looked up block {} past the head", block_number);
+            *self.lookups.lock().unwrap() += 1;
+            Ok((self.timestamp)(block_number))
+        }
+
+        async fn swap_requested_in(&self, request_id: &RequestId, from_block: u64, to_block: u64) -> eyre::Result<Option<u64>> {
+            self.searched.lock().unwrap().push((from_block, to_block));
+            Ok(self
+                .request
+                .filter(|(id, block_number)| id == request_id && (from_block..=to_block).contains(block_number))
+                .map(|(_, block_number)| block_number))
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::eth::IRouter::SwapRequestParameters;
+use crate::model::{RequestId, SkipReason};
+use alloy::primitives::{Address, B256, U256};
+use async_trait::async_trait;
+use moka::sync::Cache;
+use std::time::Duration;
+use tracing::warn;
+
+// How long we trust the outcome of checking a request against the router
+const REQUEST_CHECK_TTL: Duration = Duration::from_secs(600);
+
+// What checking a request needs from the router of its source chain
+#[async_trait]
+pub(crate) trait SwapRouter: Sync {
+    fn chain_id(&self) -> u64;
+    async fn swap_request_id(&self, params: &SwapRequestParameters) -> eyre::Result<RequestId>;
+    async fn allows_destination(&self, dst_chain_id: U256) -> eyre::Result<bool>;
+    async fn maps_token(&self, token_in: Address, dst_chain_id: U256, token_out: Address) -> eyre::Result<bool>;
+}
+
+// The outcome of checking each request against its router, redone now and then as its mappings may change.
+// A check the router fails to answer isn't kept, so it's done again on the next block.
+pub(crate) struct RequestChecks {
+    checked: Cache<RequestId, Option<SkipReason>>,
+}
+
+impl RequestChecks {
+    pub fn new() -> Self {
+        Self {
+            checked: Cache::builder().max_capacity(10_000).time_to_live(REQUEST_CHECK_TTL).build(),
+        }
+    }
+
+    // Makes sure the router's parameters for a request are consistent before we consider filling it, so that
+    // a misconfigured or malicious router can't have us fill requests it never made or won't refund
+    pub async fn check(&self, router: &impl SwapRouter, request_id: &RequestId, params: &SwapRequestParameters) -> Option<SkipReason> {
+        if let Some(rejected) = self.checked.get(request_id) {
+            return rejected;
+        }
+
+        match check_against(router, request_id, params).await {
+            Ok(rejected) => {
+                if let Some(reason) = rejected {
+                    warn!(request_id = %B256::from(*request_id), chain_id = router.chain_id(), %reason, "router returned an inconsistent swap request, refusing it");
+                }
+                self.checked.insert(*request_id, rejected);
+                rejected
+            }
+            Err(e) => {
+                warn!(request_id = %B256::from(*request_id), chain_id = router.chain_id(), error = %e, "failed to check swap request against the router, will check again");
+                Some(SkipReason::RouterCheckFailed)
+            }
+        }
+    }
+}
+
+async fn check_against(router: &impl SwapRouter, request_id: &RequestId, params: &SwapRequestParameters) -> eyre::Result<Option<SkipReason>> {
+    let rejected = if params.srcChainId != U256::from(router.chain_id()) {
+        Some(SkipReason::WrongSourceChain)
+    } else if router.swap_request_id(params).await? != *request_id {
+        Some(SkipReason::RequestIdMismatch)
+    } else if !router.allows_destination(params.dstChainId).await? {
+        Some(SkipReason::DestinationNotAllowed)
+    } else if !router.maps_token(params.tokenIn, params.dstChainId, params.tokenOut).await? {
+        Some(SkipReason::UnmappedToken)
+    } else {
+        None
+    };
+    Ok(rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eth::IRouter::SwapRequestParameters;
+    use crate::model::{RequestId, SkipReason};
+    use crate::request_checks::{RequestChecks, SwapRouter};
+    use crate::util::test::{generate_address, generate_request_id};
+    use alloy::primitives::{Address, U256};
+    use async_trait::async_trait;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn requests_the_router_gets_wrong_are_rejected() {
+        // given
+        let request_id = generate_request_id();
+        let consistent = StubbedRouter::new(request_id);
+        let other_id = StubbedRouter {
+            request_id: generate_request_id(),
+            ..StubbedRouter::new(request_id)
+        };
+        let no_destination = StubbedRouter {
+            allows_destination: false,
+            ..StubbedRouter::new(request_id)
+        };
+        let no_mapping = StubbedRouter {
+            maps_token: false,
+            ..StubbedRouter::new(request_id)
+        };
+        let from_elsewhere = SwapRequestParameters {
+            srcChainId: U256::from(3),
+            ..params()
+        };
+
+        // then
+        assert_that!(RequestChecks::new().check(&consistent, &request_id, &params()).await).is_none();
+        assert_that!(RequestChecks::new().check(&consistent, &request_id, &from_elsewhere).await).contains(SkipReason::WrongSourceChain);
+        assert_that!(RequestChecks::new().check(&other_id, &request_id, &params()).await).contains(SkipReason::RequestIdMismatch);
+        assert_that!(RequestChecks::new().check(&no_destination, &request_id, &params()).await).contains(SkipReason::DestinationNotAllowed);
+        assert_that!(RequestChecks::new().check(&no_mapping, &request_id, &params()).await).contains(SkipReason::UnmappedToken);
+    }
+
+    #[tokio::test]
+    async fn failed_checks_are_done_again_rather_than_kept() {
+        // given
+        let request_id = generate_request_id();
+        let router = StubbedRouter {
+            failing: Mutex::new(true),
+            ..StubbedRouter::new(request_id)
+        };
+        let checks = RequestChecks::new();
+
+        // when
+        let failed = checks.check(&router, &request_id, &params()).await;
+        *router.failing.lock().unwrap() = false;
+        let retried = checks.check(&router, &request_id, &params()).await;
+        let cached = checks.check(&router, &request_id, &params()).await;
+
+        // then
+        assert_that!(failed).contains(SkipReason::RouterCheckFailed);
+        assert_that!(retried).is_none();
+        assert_that!(cached).is_none();
+        assert_that!(*router.calls.lock().unwrap()).is_equal_to(4);
+    }
+
+    // The router of chain 1, which knows the request by `request_id`
+    struct StubbedRouter {
+        request_id: RequestId,
+        allows_destination: bool,
+        maps_token: bool,
+        failing: Mutex<bool>,
+        calls: Mutex<usize>,
+    }
+
+    impl StubbedRouter {
+        fn new(request_id: RequestId) -> Self {
+            Self {
+                request_id,
+                allows_destination: true,
+                maps_token: true,
+                failing: Mutex::new(false),
+                calls: Mutex::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl SwapRouter for StubbedRouter {
+        fn chain_id(&self) -> u64 {
+            1
+        }
+
+        async fn swap_request_id(&self, _: &SwapRequestParameters) -> eyre::Result<RequestId> {
+            *self.calls.lock().unwrap() += 1;
+            if *self.failing.lock().unwrap() {
+                eyre::bail!("execution reverted");
+            }
+            Ok(self.request_id)
+        }
+
+        async fn allows_destination(&self, _: U256) -> eyre::Result<bool> {
+            *self.calls.lock().unwrap() += 1;
+            Ok(self.allows_destination)
+        }
+
+        async fn maps_token(&self, _: Address, _: U256, _: Address) -> eyre::Result<bool> {
+            *self.calls.lock().unwrap() += 1;
+            Ok(self.maps_token)
+        }
+    }
+
+    fn params() -> SwapRequestParameters {
+        SwapRequestParameters {
+            sender: generate_address(),
+            recipient: generate_address(),
+            tokenIn: generate_address(),
+            tokenOut: generate_address(),
+            amountOut: U256::from(100),
+            srcChainId: U256::from(1),
+            dstChainId: U256::from(2),
+            verificationFee: U256::ZERO,
+            solverFee: U256::from(100),
+            nonce: U256::ZERO,
+            executed: false,
+            requestedAt: U256::ZERO,
+        }
+    }
+}
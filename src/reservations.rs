@@ -0,0 +1,120 @@
+use crate::model::{ChainState, RequestId};
+use alloy::primitives::{Address, U256};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+// A fill we sent that never shows up on-chain is given up on after this long
+const RESERVATION_TTL: Duration = Duration::from_secs(600);
+
+// Tokens held back for fills we've committed to but that the chain state we fetch doesn't reflect yet.
+// A reservation is made when the solver decides to fill a request, and is only released once the
+// destination chain lists the request as fulfilled (by which point its balance has dropped), or once
+// the fill reverts. Without it, a fill still waiting to be mined when the next block's state comes in
+// would leave its tokens looking free to spend, and a second fill of the same tokens would revert.
+#[derive(Default)]
+pub(crate) struct Reservations {
+    reservations: RwLock<HashMap<RequestId, Reservation>>,
+}
+
+#[derive(Debug, Clone)]
+struct Reservation {
+    chain_id: u64,
+    token: Address,
+    amount: U256,
+    reserved_at: Instant,
+}
+
+impl Reservations {
+    // A fill sent again, after sending it failed without telling us whether it went out, takes the place of
+    // the first attempt, so its tokens are only held once, for the TTL from the latest attempt
+    pub fn reserve(&self, chain_id: u64, token: Address, request_id: RequestId, amount: U256) {
+        let reservation = Reservation {
+            chain_id,
+            token,
+            amount,
+            reserved_at: Instant::now(),
+        };
+        self.reservations.write().expect("reservations lock poisoned").insert(request_id, reservation);
+    }
+
+    // For fills that failed, so their tokens never left
+    pub fn release(&self, request_id: &RequestId) {
+        self.reservations.write().expect("reservations lock poisoned").remove(request_id);
+    }
+
+    // Releases the reservations of every fill the chain now lists as fulfilled, whoever made it, along
+    // with any that have outlived the TTL
+    pub fn settle(&self, chain_id: u64, fulfilled: &[RequestId]) {
+        self.reservations
+            .write()
+            .expect("reservations lock poisoned")
+            .retain(|request_id, reservation| {
+                reservation.reserved_at.elapsed() < RESERVATION_TTL && !(reservation.chain_id == chain_id && fulfilled.contains(request_id))
+            });
+    }
+
+    pub fn reserved(&self, chain_id: u64, token: Address) -> U256 {
+        self.reservations
+            .read()
+            .expect("reservations lock poisoned")
+            .values()
+            .filter(|reservation| reservation.chain_id == chain_id && reservation.token == token && reservation.reserved_at.elapsed() < RESERVATION_TTL)
+            .fold(U256::ZERO, |total, reservation| total.saturating_add(reservation.amount))
+    }
+
+    // What's left of the chain's token balance once the tokens we've committed are taken out
+    pub fn available(&self, chain_id: u64, state: &ChainState) -> U256 {
+        state.token_balance.saturating_sub(self.reserved(chain_id, state.token_addr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reservations::Reservations;
+    use crate::util::test::{generate_address, generate_request_id};
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+
+    #[test]
+    fn reservations_are_held_until_the_fill_is_seen_on_chain() {
+        // given
+        let reservations = Reservations::default();
+        let token = generate_address();
+        let (first, second, elsewhere) = (generate_request_id(), generate_request_id(), generate_request_id());
+        reservations.reserve(1, token, first, U256::from(100));
+        reservations.reserve(1, token, second, U256::from(50));
+        reservations.reserve(2, token, elsewhere, U256::from(70));
+
+        // when
+        let before = reservations.reserved(1, token);
+        reservations.settle(2, &[first]);
+        let settled_on_another_chain = reservations.reserved(1, token);
+        reservations.settle(1, &[first]);
+        let settled = reservations.reserved(1, token);
+        reservations.release(&second);
+        let released = reservations.reserved(1, token);
+
+        // then
+        assert_that!(before).is_equal_to(U256::from(150));
+        assert_that!(settled_on_another_chain).is_equal_to(U256::from(150));
+        assert_that!(settled).is_equal_to(U256::from(50));
+        assert_that!(released).is_equal_to(U256::ZERO);
+        assert_that!(reservations.reserved(2, token)).is_equal_to(U256::from(70));
+        assert_that!(reservations.reserved(1, generate_address())).is_equal_to(U256::ZERO);
+    }
+
+    #[test]
+    fn retried_fills_are_reserved_once() {
+        // given
+        let reservations = Reservations::default();
+        let (token, request_id) = (generate_address(), generate_request_id());
+
+        // when
+        reservations.reserve(1, token, request_id, U256::from(100));
+        reservations.reserve(1, token, request_id, U256::from(100));
+
+        // then
+        assert_that!(reservations.reserved(1, token)).is_equal_to(U256::from(100));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::config::RiskConfig;
+use crate::model::{RequestId, SkipReason};
+use alloy::primitives::U256;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+// Enforces the configured risk limits on fills. Exposure is what source chains owe us for fills we've
+// made since starting, until they refund us; a fill adds its amount to the exposure of its source chain.
+#[derive(Default)]
+pub(crate) struct RiskLimits {
+    config: RiskConfig,
+    exposures: HashMap<RequestId, Exposure>, // fills awaiting their refund
+    recent_trades: VecDeque<Instant>,        // when we made the fills of the last minute
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Exposure {
+    src_chain_id: u64,
+    amount: U256,
+}
+
+impl RiskLimits {
+    pub fn set_config(&mut self, config: RiskConfig) {
+        self.config = config;
+    }
+
+    // Whether a fill of `amount` for a request from `src_chain_id` stays within every limit
+    pub fn check(&mut self, src_chain_id: u64, amount: U256) -> Result<(), SkipReason> {
+        if self.config.max_trade_amount.is_some_and(|max| amount > max) {
+            return Err(SkipReason::TradeTooLarge);
+        }
+        if self.config.max_chain_exposure.is_some_and(|max| self.chain_exposure(src_chain_id).saturating_add(amount) > max) {
+            return Err(SkipReason::ChainExposureLimit);
+        }
+        if self.config.max_total_exposure.is_some_and(|max| self.total_exposure().saturating_add(amount) > max) {
+            return Err(SkipReason::TotalExposureLimit);
+        }
+
+        while self.recent_trades.front().is_some_and(|made_at| made_at.elapsed() >= RATE_WINDOW) {
+            self.recent_trades.pop_front();
+        }
+        if self.config.max_trades_per_minute.is_some_and(|max| self.recent_trades.len() >= max as usize) {
+            return Err(SkipReason::TradeRateLimit);
+        }
+        Ok(())
+    }
+
+    pub fn record(&mut self, request_id: RequestId, src_chain_id: u64, amount: U256) {
+        self.exposures.insert(request_id, Exposure { src_chain_id, amount });
+        self.recent_trades.push_back(Instant::now());
+    }
+
+    // For fills that reverted, so the source chain owes us nothing for them
+    pub fn release(&mut self, request_id: &RequestId) {
+        self.exposures.remove(request_id);
+    }
+
+    // Fills from the chain it no longer lists as awaiting a refund have been paid out
+    pub fn refunds_received(&mut self, src_chain_id: u64, awaiting_refund: &[RequestId]) {
+        self.exposures
+            .retain(|request_id, exposure| exposure.src_chain_id != src_chain_id || awaiting_refund.contains(request_id));
+    }
+
+    pub fn chain_exposure(&self, src_chain_id: u64) -> U256 {
+        self.exposures
+            .values()
+            .filter(|exposure| exposure.src_chain_id == src_chain_id)
+            .fold(U256::ZERO, |total, exposure| total.saturating_add(exposure.amount))
+    }
+
+    pub fn total_exposure(&self) -> U256 {
+        self.exposures.values().fold(U256::ZERO, |total, exposure| total.saturating_add(exposure.amount))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::RiskConfig;
+    use crate::model::SkipReason;
+    use crate::risk::RiskLimits;
+    use crate::util::test::generate_request_id;
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn fills_past_any_limit_are_refused() {
+        // given
+        let mut limits = RiskLimits::default();
+        limits.set_config(RiskConfig {
+            max_trade_amount: Some(U256::from(500)),
+            max_chain_exposure: Some(U256::from(800)),
+            max_total_exposure: Some(U256::from(1000)),
+            max_trades_per_minute: Some(3),
+        });
+        let first = generate_request_id();
+        limits.record(first, 1, U256::from(500));
+        limits.record(generate_request_id(), 2, U256::from(300));
+
+        // then
+        assert_that!(limits.check(1, U256::from(501))).is_err_containing(SkipReason::TradeTooLarge);
+        assert_that!(limits.check(1, U256::from(301))).is_err_containing(SkipReason::ChainExposureLimit);
+        assert_that!(limits.check(3, U256::from(201))).is_err_containing(SkipReason::TotalExposureLimit);
+        assert_that!(limits.check(3, U256::from(200))).is_ok();
+
+        limits.record(generate_request_id(), 3, U256::from(1));
+        assert_that!(limits.check(3, U256::from(1))).is_err_containing(SkipReason::TradeRateLimit);
+    }
+
+    #[test]
+    fn refunds_free_up_exposure() {
+        // given
+        let mut limits = RiskLimits::default();
+        let (refunded, awaiting) = (generate_request_id(), generate_request_id());
+        limits.record(refunded, 1, U256::from(500));
+        limits.record(awaiting, 1, U256::from(300));
+        limits.record(generate_request_id(), 2, U256::from(200));
+
+        // when
+        limits.refunds_received(1, &[awaiting]);
+
+        // then
+        assert_that!(limits.chain_exposure(1)).is_equal_to(U256::from(300));
+        assert_that!(limits.total_exposure()).is_equal_to(U256::from(500));
+    }
+
+    #[test]
+    fn reverted_fills_owe_us_nothing() {
+        // given
+        let mut limits = RiskLimits::default();
+        let (reverted, landed) = (generate_request_id(), generate_request_id());
+        limits.record(reverted, 1, U256::from(500));
+        limits.record(landed, 1, U256::from(300));
+
+        // when
+        limits.release(&reverted);
+
+        // then
+        assert_that!(limits.chain_exposure(1)).is_equal_to(U256::from(300));
+    }
+}
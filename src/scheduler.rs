@@ -0,0 +1,154 @@
+use crate::model::{RequestId, SkipReason, Transfer};
+use crate::util::normalise_chain_id;
+use std::collections::{HashMap, HashSet};
+
+// Decides which transfers a block calls for evaluating again. A block only changes the state of the
+// chain it's from, and auctions run on the clock of their source chain, so a transfer neither from nor to
+// that chain prices and fits as it did last time. Such a transfer keeps its last outcome, unless that
+// outcome hangs on something besides chain state (operator controls, risk limits, fills in flight, or the
+// inventory the fills of other transfers take).
+#[derive(Debug, Default)]
+pub(crate) struct Scheduler {
+    everything_due: bool, // set when the solver's settings or chains change
+    held: HashMap<RequestId, SkipReason>, // why each transfer wasn't filled when we last evaluated it
+}
+
+impl Scheduler {
+    // Evaluates every transfer in the next round
+    pub fn invalidate(&mut self) {
+        self.everything_due = true;
+    }
+
+    // The outcome of a transfer that needn't be evaluated after a block on `chain_id`, if any
+    pub fn held(&self, transfer: &Transfer, chain_id: u64) -> Option<SkipReason> {
+        if self.everything_due || normalise_chain_id(transfer.params.srcChainId) == chain_id || normalise_chain_id(transfer.params.dstChainId) == chain_id {
+            return None;
+        }
+        self.held.get(&transfer.request_id).copied().filter(|reason| depends_only_on_chain_state(*reason))
+    }
+
+    // `outcome` is why the transfer wasn't filled, or none if it was picked to be
+    pub fn evaluated(&mut self, request_id: RequestId, outcome: Option<SkipReason>) {
+        match outcome {
+            Some(reason) => self.held.insert(request_id, reason),
+            None => self.held.remove(&request_id),
+        };
+    }
+
+    // Ends a round, forgetting transfers no chain lists any more
+    pub fn finish_round(&mut self, live: &HashSet<RequestId>) {
+        self.everything_due = false;
+        self.held.retain(|request_id, _| live.contains(request_id));
+    }
+}
+
+// Whether a skip can only change along with the state of the transfer's source or destination chain.
+// Settings the skip depends on, like the strategy or address lists, invalidate the whole schedule instead.
+fn depends_only_on_chain_state(reason: SkipReason) -> bool {
+    match reason {
+        SkipReason::UnknownDestination
+        | SkipReason::AlreadyExecuted
+        | SkipReason::AlreadyFulfilled
+        | SkipReason::NoNativeBalance
+        | SkipReason::SlippageTooHigh
+        | SkipReason::TokenMismatch
+        | SkipReason::DeniedAddress
+        | SkipReason::NotAllowListed
+        | SkipReason::WrongSourceChain
+        | SkipReason::RequestIdMismatch
+        | SkipReason::UnmappedToken
+        | SkipReason::DestinationNotAllowed
+        | SkipReason::UnknownRequestTime
+        | SkipReason::RouterCheckFailed
+        | SkipReason::OwnRequest => true,
+        // whether a transfer fits depends on which others are chosen and on what's reserved for fills not yet mined
+        SkipReason::InsufficientTokens
+        // an auction's price comes down with time and the drand randomness of the round, not just its chains' state
+        | SkipReason::PriceTooHigh
+        | SkipReason::InFlight
+        | SkipReason::Paused
+        | SkipReason::Draining
+        | SkipReason::ChainPaused
+        | SkipReason::RequestSkipped
+        | SkipReason::TradeTooLarge
+        | SkipReason::ChainExposureLimit
+        | SkipReason::TotalExposureLimit
+        | SkipReason::TradeRateLimit
+        | SkipReason::CircuitOpen => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eth::IRouter::SwapRequestParameters;
+    use crate::model::{SkipReason, Transfer};
+    use crate::scheduler::Scheduler;
+    use crate::util::test::generate_request_id;
+    use alloy::primitives::{Address, U256};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn only_transfers_touching_the_changed_chain_are_evaluated_again() {
+        // given
+        let mut scheduler = Scheduler::default();
+        let (one_to_two, two_to_three, paused, short, pricey) = (transfer(1, 2), transfer(2, 3), transfer(2, 3), transfer(2, 3), transfer(2, 3));
+        scheduler.evaluated(one_to_two.request_id, Some(SkipReason::TokenMismatch));
+        scheduler.evaluated(two_to_three.request_id, Some(SkipReason::TokenMismatch));
+        scheduler.evaluated(paused.request_id, Some(SkipReason::Paused));
+        scheduler.evaluated(short.request_id, Some(SkipReason::InsufficientTokens));
+        scheduler.evaluated(pricey.request_id, Some(SkipReason::PriceTooHigh));
+
+        // then
+        assert_that!(scheduler.held(&one_to_two, 1)).is_none();
+        assert_that!(scheduler.held(&one_to_two, 2)).is_none();
+        assert_that!(scheduler.held(&one_to_two, 3)).contains(SkipReason::TokenMismatch);
+        assert_that!(scheduler.held(&two_to_three, 1)).contains(SkipReason::TokenMismatch);
+        assert_that!(scheduler.held(&paused, 1)).is_none();
+        assert_that!(scheduler.held(&short, 1)).is_none();
+        assert_that!(scheduler.held(&pricey, 1)).is_none();
+        assert_that!(scheduler.held(&transfer(2, 3), 1)).is_none();
+    }
+
+    #[test]
+    fn invalidating_evaluates_everything_for_a_round() {
+        // given
+        let mut scheduler = Scheduler::default();
+        let (held, gone) = (transfer(1, 2), transfer(1, 2));
+        scheduler.evaluated(held.request_id, Some(SkipReason::TokenMismatch));
+        scheduler.evaluated(gone.request_id, Some(SkipReason::TokenMismatch));
+
+        // when
+        scheduler.invalidate();
+        let while_invalid = scheduler.held(&held, 3);
+        scheduler.finish_round(&HashSet::from([held.request_id]));
+
+        // then
+        assert_that!(while_invalid).is_none();
+        assert_that!(scheduler.held(&held, 3)).contains(SkipReason::TokenMismatch);
+        assert_that!(scheduler.held(&gone, 3)).is_none();
+    }
+
+    fn transfer(src_chain_id: u64, dst_chain_id: u64) -> Transfer {
+        Transfer {
+            request_id: generate_request_id(),
+            params: SwapRequestParameters {
+                sender: Address::ZERO,
+                recipient: Address::ZERO,
+                tokenIn: Address::ZERO,
+                tokenOut: Address::ZERO,
+                amountOut: U256::from(100),
+                srcChainId: U256::from(src_chain_id),
+                dstChainId: U256::from(dst_chain_id),
+                verificationFee: U256::ZERO,
+                solverFee: U256::from(100),
+                nonce: U256::ZERO,
+                executed: false,
+                requestedAt: U256::ZERO,
+            },
+            requested_block: None,
+            rejected: None,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use crate::model::mul_div;
+use alloy::primitives::{U256, U512};
+use std::cmp::Ordering;
+
+// Up to this many competing fills we search for the best subset; beyond it we settle for a heuristic
+const EXACT_SEARCH_LIMIT: usize = 20;
+
+// A fill competing for the inventory of its destination chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Candidate {
+    pub amount: U256, // tokens the fill sends
+    // What the fill earns, less what it costs us, in tokens of the destination chain: its auction margin, the
+    // amount less the price its auction came down to, less the chain's fill cost
+    pub profit: U256,
+}
+
+impl Candidate {
+    pub fn new(amount: U256, auction_price: U256, fill_cost: U256) -> Self {
+        Self {
+            amount,
+            profit: amount.saturating_sub(auction_price).saturating_sub(fill_cost),
+        }
+    }
+}
+
+// Chooses which fills to make with `capacity` tokens, maximising the total profit (a 0/1 knapsack).
+// Whatever still fits after that is filled too, as fills that earn nothing still serve users.
+// Returns the indices of the chosen candidates, in order.
+pub(crate) fn select(candidates: &[Candidate], capacity: U256) -> Vec<usize> {
+    let total = candidates.iter().fold(U256::ZERO, |total, c| total.saturating_add(c.amount));
+    if total <= capacity {
+        return (0..candidates.len()).collect();
+    }
+
+    let fitting: Vec<usize> = (0..candidates.len()).filter(|i| candidates[*i].amount <= capacity).collect();
+    let mut chosen = if fitting.len() <= EXACT_SEARCH_LIMIT {
+        branch_and_bound(candidates, &fitting, capacity)
+    } else {
+        greedy(candidates, &fitting, capacity)
+    };
+
+    // top up with whatever still fits, smallest first
+    let mut remaining = capacity - chosen.iter().fold(U256::ZERO, |total, i| total + candidates[*i].amount);
+    let mut rest: Vec<usize> = fitting.into_iter().filter(|i| !chosen.contains(i)).collect();
+    rest.sort_by_key(|i| candidates[*i].amount);
+    for i in rest {
+        if candidates[i].amount <= remaining {
+            remaining -= candidates[i].amount;
+            chosen.push(i);
+        }
+    }
+
+    chosen.sort_unstable();
+    chosen
+}
+
+// Most profit per token first
+fn by_density(candidates: &[Candidate], a: usize, b: usize) -> Ordering {
+    let (a, b) = (&candidates[a], &candidates[b]);
+    (U512::from(b.profit) * U512::from(a.amount)).cmp(&(U512::from(a.profit) * U512::from(b.amount)))
+}
+
+// Takes the densest fills that fit, unless the single most profitable fill beats them; never worse
+// than half the best total
+fn greedy(candidates: &[Candidate], fitting: &[usize], capacity: U256) -> Vec<usize> {
+    let mut order = fitting.to_vec();
+    order.sort_by(|a, b| by_density(candidates, *a, *b));
+
+    let mut chosen = Vec::new();
+    let (mut remaining, mut profit) = (capacity, U256::ZERO);
+    for i in order {
+        if candidates[i].amount <= remaining {
+            remaining -= candidates[i].amount;
+            profit = profit.saturating_add(candidates[i].profit);
+            chosen.push(i);
+        }
+    }
+
+    match fitting.iter().max_by_key(|i| candidates[**i].profit) {
+        Some(best) if candidates[*best].profit > profit => vec![*best],
+        _ => chosen,
+    }
+}
+
+// Depth-first search over taking or leaving each fill, densest first, pruning branches that can't beat
+// the best found so far even if the rest of the space were filled at the density of the next fill
+fn branch_and_bound(candidates: &[Candidate], fitting: &[usize], capacity: U256) -> Vec<usize> {
+    struct Search<'a> {
+        candidates: &'a [Candidate],
+        order: Vec<usize>,
+        best_profit: U256,
+        best: Vec<usize>,
+        taken: Vec<usize>,
+    }
+
+    impl Search<'_> {
+        fn explore(&mut self, depth: usize, remaining: U256, profit: U256) {
+            if profit > self.best_profit {
+                self.best_profit = profit;
+                self.best = self.taken.clone();
+            }
+            if depth == self.order.len() || self.bound(depth, remaining, profit) <= self.best_profit {
+                return;
+            }
+
+            let i = self.order[depth];
+            let candidate = self.candidates[i];
+            if candidate.amount <= remaining {
+                self.taken.push(i);
+                self.explore(depth + 1, remaining - candidate.amount, profit.saturating_add(candidate.profit));
+                self.taken.pop();
+            }
+            self.explore(depth + 1, remaining, profit);
+        }
+
+        // The fractional relaxation: fill greedily by density and take a share of the first that doesn't fit
+        fn bound(&self, depth: usize, mut remaining: U256, mut profit: U256) -> U256 {
+            for i in &self.order[depth..] {
+                let candidate = self.candidates[*i];
+                if candidate.amount <= remaining {
+                    remaining -= candidate.amount;
+                    profit = profit.saturating_add(candidate.profit);
+                } else {
+                    return profit.saturating_add(mul_div(candidate.profit, remaining, candidate.amount));
+                }
+            }
+            profit
+        }
+    }
+
+    let mut order = fitting.to_vec();
+    order.sort_by(|a, b| by_density(candidates, *a, *b));
+    let mut search = Search {
+        candidates,
+        order,
+        best_profit: U256::ZERO,
+        best: Vec::new(),
+        taken: Vec::new(),
+    };
+    search.explore(0, capacity, U256::ZERO);
+    search.best
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::selection::{Candidate, EXACT_SEARCH_LIMIT, select};
+    use alloy::primitives::U256;
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn selection_beats_first_come_first_served() {
+        // given
+        // the first fill is the most profitable on its own, but the two after it earn more together
+        let candidates = vec![candidate(60, 30), candidate(50, 25), candidate(50, 25)];
+
+        // when
+        let chosen = select(&candidates, U256::from(100));
+
+        // then
+        assert_that!(chosen).is_equal_to(vec![1, 2]);
+        assert_that!(profit(&candidates, &chosen)).is_greater_than(profit(&candidates, &fifo(&candidates, 100)));
+    }
+
+    #[test]
+    fn selection_finds_the_best_subset_where_densest_first_does_not() {
+        // given
+        // taking the densest fill first leaves room for nothing else worth having
+        let candidates = vec![candidate(30, 40), candidate(50, 60), candidate(50, 60), candidate(25, 5)];
+
+        // when
+        let chosen = select(&candidates, U256::from(100));
+
+        // then
+        assert_that!(profit(&candidates, &chosen)).is_equal_to(U256::from(120));
+        assert_that!(profit(&candidates, &chosen)).is_greater_than(profit(&candidates, &fifo(&candidates, 100)));
+    }
+
+    #[test]
+    fn large_selections_fall_back_to_a_heuristic_that_still_beats_first_come_first_served() {
+        // given
+        let mut candidates = vec![candidate(1000, 10)];
+        candidates.extend((0..EXACT_SEARCH_LIMIT * 2).map(|_| candidate(40, 5)));
+
+        // when
+        let chosen = select(&candidates, U256::from(1000));
+
+        // then
+        assert_that!(chosen).has_length(25);
+        assert_that!(profit(&candidates, &chosen)).is_equal_to(U256::from(125));
+        assert_that!(profit(&candidates, &chosen)).is_greater_than(profit(&candidates, &fifo(&candidates, 1000)));
+    }
+
+    #[test]
+    fn unprofitable_fills_are_still_made_if_they_fit() {
+        let candidates = vec![candidate(60, 0), candidate(50, 10), candidate(30, 0)];
+        assert_that!(select(&candidates, U256::from(90))).is_equal_to(vec![1, 2]);
+        assert_that!(select(&candidates, U256::from(200))).is_equal_to(vec![0, 1, 2]);
+        assert_that!(select(&candidates, U256::from(10))).is_empty();
+    }
+
+    fn candidate(amount: u64, profit: u64) -> Candidate {
+        Candidate {
+            amount: U256::from(amount),
+            profit: U256::from(profit),
+        }
+    }
+
+    fn profit(candidates: &[Candidate], chosen: &[usize]) -> U256 {
+        chosen.iter().map(|i| candidates[*i].profit).sum()
+    }
+
+    // What filling in the order requests come in gets
+    fn fifo(candidates: &[Candidate], capacity: u64) -> Vec<usize> {
+        let mut remaining = U256::from(capacity);
+        let mut chosen = Vec::new();
+        for (i, candidate) in candidates.iter().enumerate() {
+            if candidate.amount <= remaining {
+                remaining -= candidate.amount;
+                chosen.push(i);
+            }
+        }
+        chosen
+    }
+}
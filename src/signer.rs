@@ -0,0 +1,286 @@
+use crate::config::{NetworkConfig, SignerConfig};
+use alloy::consensus::transaction::SignerRecoverable;
+use alloy::consensus::{TxEnvelope, TypedTransaction};
+use alloy::eips::eip2718::Decodable2718;
+use alloy::network::{Ethereum, EthereumWallet, NetworkWallet};
+use alloy::primitives::{Address, Bytes};
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::local::PrivateKeySigner;
+use eyre::WrapErr;
+use serde::Deserialize;
+use shellexpand::tilde;
+use std::fs;
+use std::str::FromStr;
+use url::Url;
+
+// Signs the solver's transactions, either with a key held in memory or through an external signer
+#[derive(Debug, Clone)]
+pub(crate) enum SolverSigner {
+    Local(EthereumWallet),
+    Remote(RemoteSigner),
+}
+
+// The signer for each chain: its own if it has one configured, otherwise the one from the command line
+#[derive(Debug, Clone)]
+pub(crate) struct Signers {
+    default: Option<SolverSigner>,
+}
+
+// Delegates signing to a service speaking the `eth_signTransaction` JSON-RPC method, e.g. web3signer
+// or clef, so the key never has to be in the solver's memory
+#[derive(Debug, Clone)]
+pub(crate) struct RemoteSigner {
+    url: Url,
+    address: Address,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<SignedTransaction>,
+    error: Option<JsonRpcError>,
+}
+
+#[derive(Deserialize)]
+struct JsonRpcError {
+    code: i64,
+    message: String,
+}
+
+// web3signer returns the raw transaction, while geth and clef wrap it in an object
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SignedTransaction {
+    Raw(Bytes),
+    WithTx { raw: Bytes },
+}
+
+impl SolverSigner {
+    pub fn load(config: &SignerConfig) -> eyre::Result<Self> {
+        match config {
+            SignerConfig::PrivateKey { private_key } => {
+                let signer = PrivateKeySigner::from_str(private_key).wrap_err("invalid private key")?;
+                Ok(SolverSigner::Local(EthereumWallet::new(signer)))
+            }
+            SignerConfig::Keystore { path, password_file } => {
+                let password = fs::read_to_string(tilde(password_file).as_ref())
+                    .wrap_err_with(|| format!("failed to read keystore password file at {}", password_file))?;
+                let signer = PrivateKeySigner::decrypt_keystore(tilde(path).as_ref(), password.trim_end_matches(['\r', '\n']))
+                    .wrap_err_with(|| format!("failed to decrypt keystore at {}", path))?;
+                Ok(SolverSigner::Local(EthereumWallet::new(signer)))
+            }
+            SignerConfig::Remote { url, address } => {
+                let url = Url::parse(url).wrap_err_with(|| format!("invalid remote signer url {}", url))?;
+                Ok(SolverSigner::Remote(RemoteSigner::new(url, *address)))
+            }
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        match self {
+            SolverSigner::Local(wallet) => NetworkWallet::<Ethereum>::default_signer_address(wallet),
+            SolverSigner::Remote(remote) => remote.address,
+        }
+    }
+}
+
+impl Signers {
+    pub fn new(default: Option<SolverSigner>) -> Self {
+        Self { default }
+    }
+
+    pub fn for_network(&self, config: &NetworkConfig) -> eyre::Result<SolverSigner> {
+        match (&config.signer, &self.default) {
+            (Some(signer), _) => SolverSigner::load(signer).wrap_err_with(|| format!("failed to load the signer for chain {}", config.chain_id)),
+            (None, Some(default)) => Ok(default.clone()),
+            (None, None) => eyre::bail!("chain {} has no signer configured, and none was given on the command line", config.chain_id),
+        }
+    }
+}
+
+impl NetworkWallet<Ethereum> for SolverSigner {
+    fn default_signer_address(&self) -> Address {
+        self.address()
+    }
+
+    fn has_signer_for(&self, address: &Address) -> bool {
+        *address == self.address()
+    }
+
+    fn signer_addresses(&self) -> impl Iterator<Item = Address> {
+        std::iter::once(self.address())
+    }
+
+    async fn sign_transaction_from(&self, sender: Address, tx: TypedTransaction) -> alloy::signers::Result<TxEnvelope> {
+        match self {
+            SolverSigner::Local(wallet) => NetworkWallet::<Ethereum>::sign_transaction_from(wallet, sender, tx).await,
+            SolverSigner::Remote(remote) => remote.sign_transaction(sender, tx).await,
+        }
+    }
+}
+
+impl RemoteSigner {
+    pub fn new(url: Url, address: Address) -> Self {
+        Self {
+            url,
+            address,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn sign_transaction(&self, sender: Address, tx: TypedTransaction) -> alloy::signers::Result<TxEnvelope> {
+        if sender != self.address {
+            return Err(alloy::signers::Error::message(format!("remote signer signs for {}, not {}", self.address, sender)));
+        }
+
+        let tx: TransactionRequest = tx.into();
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_signTransaction",
+            "params": [tx.from(sender)],
+        });
+        let response: JsonRpcResponse = self
+            .client
+            .post(self.url.clone())
+            .json(&request)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(alloy::signers::Error::other)?
+            .json()
+            .await
+            .map_err(alloy::signers::Error::other)?;
+
+        let raw = match (response.result, response.error) {
+            (_, Some(error)) => return Err(alloy::signers::Error::message(format!("remote signer error {}: {}", error.code, error.message))),
+            (Some(SignedTransaction::Raw(raw)), None) | (Some(SignedTransaction::WithTx { raw }), None) => raw,
+            (None, None) => return Err(alloy::signers::Error::message("remote signer returned neither a result nor an error")),
+        };
+
+        // don't trust the signer to have signed what we asked for, with the key we expect
+        let envelope = TxEnvelope::decode_2718(&mut raw.as_ref()).map_err(alloy::signers::Error::other)?;
+        let signed_by = envelope.recover_signer().map_err(alloy::signers::Error::other)?;
+        if signed_by != sender {
+            return Err(alloy::signers::Error::message(format!("remote signer signed with {} instead of {}", signed_by, sender)));
+        }
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::SignerConfig;
+    use crate::signer::{RemoteSigner, Signers, SolverSigner};
+    use crate::util::test::network_config;
+    use alloy::consensus::transaction::SignerRecoverable;
+    use alloy::consensus::{TxEip1559, TypedTransaction};
+    use alloy::eips::eip2718::Encodable2718;
+    use alloy::network::{EthereumWallet, TransactionBuilder};
+    use alloy::primitives::{Address, TxKind, U256, hex};
+    use alloy::rpc::types::TransactionRequest;
+    use alloy::signers::local::PrivateKeySigner;
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{Value, json};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use url::Url;
+
+    #[tokio::test]
+    async fn remote_signers_sign_transactions_over_json_rpc() {
+        // given
+        let key = PrivateKeySigner::random();
+        let url = stub_signer(key.clone()).await;
+        let signer = RemoteSigner::new(url, key.address());
+
+        // when
+        let envelope = signer.sign_transaction(key.address(), transaction()).await.unwrap();
+
+        // then
+        assert_that!(envelope.recover_signer().unwrap()).is_equal_to(key.address());
+    }
+
+    #[tokio::test]
+    async fn transactions_signed_with_the_wrong_key_are_rejected() {
+        // given
+        let url = stub_signer(PrivateKeySigner::random()).await;
+        let expected = PrivateKeySigner::random().address();
+        let signer = RemoteSigner::new(url, expected);
+
+        // when
+        let result = signer.sign_transaction(expected, transaction()).await;
+
+        // then
+        assert_that!(result.unwrap_err().to_string()).contains("remote signer signed with");
+    }
+
+    #[tokio::test]
+    async fn keystores_are_decrypted_with_the_password_file() {
+        // given
+        let dir = std::env::temp_dir().join(format!("solver-keystore-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (key, name) = PrivateKeySigner::new_keystore(&dir, &mut rand::thread_rng(), "hunter2", None).unwrap();
+        let password_file = dir.join("password");
+        std::fs::write(&password_file, "hunter2\n").unwrap();
+        let config = SignerConfig::Keystore {
+            path: dir.join(name).to_str().unwrap().to_string(),
+            password_file: password_file.to_str().unwrap().to_string(),
+        };
+
+        // when
+        let signer = SolverSigner::load(&config);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // then
+        assert_that!(signer.unwrap().address()).is_equal_to(key.address());
+    }
+
+    #[test]
+    fn networks_use_their_own_signer_or_the_default() {
+        // given
+        let default = PrivateKeySigner::random();
+        let own = PrivateKeySigner::random();
+        let signers = Signers::new(Some(SolverSigner::Local(EthereumWallet::new(default.clone()))));
+        let mut network = network_config(1);
+        network.signer = Some(SignerConfig::PrivateKey {
+            private_key: hex::encode(own.to_bytes()),
+        });
+
+        // then
+        assert_that!(signers.for_network(&network).unwrap().address()).is_equal_to(own.address());
+        assert_that!(signers.for_network(&network_config(2)).unwrap().address()).is_equal_to(default.address());
+        assert_that!(Signers::new(None).for_network(&network_config(2)).unwrap_err().to_string()).contains("chain 2 has no signer configured");
+    }
+
+    fn transaction() -> TypedTransaction {
+        TypedTransaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_per_gas: 2,
+            max_priority_fee_per_gas: 1,
+            to: TxKind::Call(Address::ZERO),
+            value: U256::from(1),
+            ..Default::default()
+        })
+    }
+
+    // Serves `eth_signTransaction` by signing with `key`, like a remote signer would
+    async fn stub_signer(key: PrivateKeySigner) -> Url {
+        async fn sign(State(wallet): State<EthereumWallet>, Json(request): Json<Value>) -> Json<Value> {
+            let mut tx: TransactionRequest = serde_json::from_value(request["params"][0].clone()).unwrap();
+            tx.from = None; // sign with whatever key we have, even if it isn't the one asked for
+            let envelope = tx.build(&wallet).await.unwrap();
+            Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": hex::encode_prefixed(envelope.encoded_2718()) }))
+        }
+
+        let wallet = EthereumWallet::new(key);
+        let app = Router::new().route("/", post(sign)).with_state(wallet);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+}
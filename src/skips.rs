@@ -0,0 +1,142 @@
+use crate::model::{FULL_BPS, SkipReason, Transfer, mul_div};
+use crate::status::decimal;
+use crate::util::normalise_chain_id;
+use alloy::primitives::{B256, U256};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// Every transfer the solver didn't fill in one evaluation round, i.e. one block, and why
+#[derive(Serialize, Debug, Clone, Default)]
+pub(crate) struct SkipReport {
+    pub chain_id: u64, // chain whose block triggered the round
+    pub block_number: u64,
+    pub by_chain: BTreeMap<u64, ChainSkips>, // keyed by destination chain, where the fill would have happened
+    pub requests: Vec<SkippedRequest>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ChainSkips {
+    pub requests: usize,
+    #[serde(serialize_with = "decimal")]
+    pub volume: U256,
+    pub by_reason: BTreeMap<SkipReason, SkipTotals>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SkipTotals {
+    pub requests: usize,
+    #[serde(serialize_with = "decimal")]
+    pub volume: U256,
+    pub volume_share_bps: u64, // of all the volume skipped on the chain
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SkippedRequest {
+    pub request_id: B256,
+    pub src_chain_id: u64,
+    pub dest_chain_id: u64,
+    #[serde(serialize_with = "decimal")]
+    pub amount: U256,
+    pub reason: SkipReason,
+}
+
+impl SkipReport {
+    pub fn new(chain_id: u64, block_number: u64) -> Self {
+        Self {
+            chain_id,
+            block_number,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, transfer: &Transfer, reason: SkipReason) {
+        let dest_chain_id = normalise_chain_id(transfer.params.dstChainId);
+        let amount = transfer.params.amountOut;
+
+        let chain = self.by_chain.entry(dest_chain_id).or_default();
+        chain.requests += 1;
+        chain.volume = chain.volume.saturating_add(amount);
+        let totals = chain.by_reason.entry(reason).or_default();
+        totals.requests += 1;
+        totals.volume = totals.volume.saturating_add(amount);
+        for totals in chain.by_reason.values_mut() {
+            totals.volume_share_bps = mul_div(totals.volume, U256::from(FULL_BPS), chain.volume).saturating_to();
+        }
+
+        self.requests.push(SkippedRequest {
+            request_id: B256::from(transfer.request_id),
+            src_chain_id: normalise_chain_id(transfer.params.srcChainId),
+            dest_chain_id,
+            amount,
+            reason,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eth::IRouter::SwapRequestParameters;
+    use crate::model::{SkipReason, Transfer};
+    use crate::skips::SkipReport;
+    use alloy::primitives::{Address, U256};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+
+    #[test]
+    fn skips_are_aggregated_per_destination_chain_and_reason() {
+        // given
+        let mut report = SkipReport::new(1, 100);
+
+        // when
+        report.record(&transfer(1, 84532, 600), SkipReason::InsufficientTokens);
+        report.record(&transfer(2, 84532, 400), SkipReason::PriceTooHigh);
+        report.record(&transfer(3, 84532, 1000), SkipReason::PriceTooHigh);
+        report.record(&transfer(4, 43113, 5), SkipReason::NoNativeBalance);
+
+        // then
+        let chain = &report.by_chain[&84532];
+        assert_that!(chain.requests).is_equal_to(3);
+        assert_that!(chain.volume).is_equal_to(U256::from(2000));
+        assert_that!(chain.by_reason[&SkipReason::InsufficientTokens].volume_share_bps).is_equal_to(3000);
+        assert_that!(chain.by_reason[&SkipReason::PriceTooHigh].requests).is_equal_to(2);
+        assert_that!(chain.by_reason[&SkipReason::PriceTooHigh].volume_share_bps).is_equal_to(7000);
+        assert_that!(report.by_chain[&43113].by_reason[&SkipReason::NoNativeBalance].volume_share_bps).is_equal_to(10_000);
+        assert_that!(report.requests).has_length(4);
+    }
+
+    #[test]
+    fn reports_serialise_reasons_as_keys() {
+        // given
+        let mut report = SkipReport::new(1, 100);
+        report.record(&transfer(1, 2, 600), SkipReason::InsufficientTokens);
+
+        // when
+        let json = serde_json::to_value(&report).unwrap();
+
+        // then
+        assert_that!(json["by_chain"]["2"]["by_reason"]["insufficient_tokens"]["volume"]).is_equal_to(serde_json::json!("600"));
+        assert_that!(json["requests"][0]["reason"]).is_equal_to(serde_json::json!("insufficient_tokens"));
+    }
+
+    fn transfer(id: u8, dest_chain_id: u64, amount: u64) -> Transfer {
+        Transfer {
+            request_id: [id; 32],
+            params: SwapRequestParameters {
+                sender: Address::ZERO,
+                recipient: Address::ZERO,
+                tokenIn: Address::ZERO,
+                tokenOut: Address::ZERO,
+                amountOut: U256::from(amount),
+                srcChainId: U256::from(1),
+                dstChainId: U256::from(dest_chain_id),
+                verificationFee: U256::ZERO,
+                solverFee: U256::from(100),
+                nonce: U256::ZERO,
+                executed: false,
+                requestedAt: U256::ZERO,
+            },
+            requested_block: None,
+            rejected: None,
+        }
+    }
+}
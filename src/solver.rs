@@ -1,140 +1,331 @@
+use crate::admin::AdminControls;
+use crate::compliance::Compliance;
+use crate::config::RiskConfig;
+use crate::drand::DrandRandomness;
 use crate::eth::IRouter::SwapRequestParameters;
-use crate::model::{ChainState, RequestId, Trade, Transfer, DutchAuction};
-use crate::util::normalise_chain_id;
-use alloy::primitives::U256;
+use crate::events::SolverEvent;
+use crate::metrics::Metrics;
+use crate::model::{BlockEvent, ChainState, DecayCurve, DutchAuction, FULL_BPS, RequestId, SkipReason, Trade, Transfer, apply_bps};
+use crate::reservations::Reservations;
+use crate::risk::RiskLimits;
+use crate::scheduler::Scheduler;
+use crate::selection::{Candidate, select};
+use crate::skips::SkipReport;
+use crate::status::SolverStatus;
+use crate::util::{normalise_chain_id, request_span};
+use alloy::primitives::{B256, U256};
 use async_trait::async_trait;
 use moka::sync::Cache;
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tracing::{Instrument, debug, info, warn};
 
 #[async_trait]
 pub(crate) trait ChainStateProvider {
     async fn fetch_state(&self) -> eyre::Result<ChainState>;
 }
 
-pub(crate) struct Solver<'a, CSP> {
+pub(crate) struct Solver<CSP> {
     states: HashMap<u64, ChainState>,
-    chains: &'a HashMap<u64, CSP>,
-    initial_transfers: HashMap<u64, Vec<RequestId>>, // Track transfers that existed at startup
-    demo_mode: bool, // Allow processing of pre-fulfilled transfers for demo purposes
+    chains: HashMap<u64, Arc<CSP>>,
+    demo_transfers: Option<HashMap<u64, Vec<RequestId>>>, // only in demo mode: the transfers there were when it was turned on, auctioned even once fulfilled
     threshold_multiplier: f64, // Configurable threshold multiplier for this solver
     solver_name: String, // Name/ID for this solver instance
+    decay_curve: DecayCurve, // Shape of the price curve for auctions this solver starts
+    metrics: Arc<Metrics>,
+    status: Arc<SolverStatus>, // what we publish for the API to show
+    controls: Arc<AdminControls>, // operator pauses, drains and skips
+    reservations: Arc<Reservations>, // tokens committed to fills the chain state doesn't reflect yet
+    fill_costs: HashMap<u64, U256>, // what a fill on each chain costs us in tokens, gas included
+    risk: Arc<Mutex<RiskLimits>>, // shared with the executor, which undoes the exposure of fills that revert
+    compliance: Compliance, // who we may serve
+    scheduler: Scheduler, // which transfers a block calls for evaluating again
+    drand: DrandRandomness,
+    randomness: Option<u64>, // drand randomness for the round, fetched when the first auction of it is priced
 }
-impl<'a, CSP: ChainStateProvider> Solver<'a, CSP> {
-    pub async fn from(chains: &'a HashMap<u64, CSP>, threshold_multiplier: f64, solver_name: String) -> eyre::Result<Self> {
+impl<CSP: ChainStateProvider> Solver<CSP> {
+    pub async fn from(chains: &HashMap<u64, Arc<CSP>>, threshold_multiplier: f64, solver_name: String, decay_curve: DecayCurve, metrics: Arc<Metrics>, status: Arc<SolverStatus>, controls: Arc<AdminControls>) -> eyre::Result<Self> {
         let mut states: HashMap<u64, ChainState> = HashMap::new();
-        let mut initial_transfers: HashMap<u64, Vec<RequestId>> = HashMap::new();
 
         // fetch the initial state for each chain before we listen for blocks
         for (chain_id, chain) in chains {
             let state = chain.fetch_state().await?;
-            
-            // Record initial transfers to distinguish from new ones
-            let initial_transfer_ids = state.transfers.iter()
-                .map(|t| t.request_id)
-                .collect();
-            initial_transfers.insert(*chain_id, initial_transfer_ids);
-            
             states.insert(*chain_id, state);
         }
 
-        println!("🔧 Initialized solver '{}' with threshold multiplier: {}x", solver_name, threshold_multiplier);
-        Ok(Self { 
+        info!(solver = %solver_name, threshold_multiplier, ?decay_curve, "initialized solver");
+        let mut solver = Self { 
             states, 
-            chains, 
-            initial_transfers, 
-            demo_mode: true,
+            chains: chains.clone(), 
+            demo_transfers: None,
             threshold_multiplier,
             solver_name,
-        })
+            decay_curve,
+            metrics,
+            status,
+            controls,
+            reservations: Arc::new(Reservations::default()),
+            fill_costs: HashMap::new(),
+            risk: Arc::new(Mutex::new(RiskLimits::default())),
+            compliance: Compliance::default(),
+            scheduler: Scheduler::default(),
+            drand: DrandRandomness::new(),
+            randomness: None,
+        };
+
+        // auctions are anchored on-chain, so after a restart we pick up where every other solver is
+        for chain_id in chains.keys() {
+            solver.start_auctions_for_new_transfers(*chain_id);
+        }
+        solver.publish_states();
+        Ok(solver)
+    }
+    // Starts solving on a newly configured chain, or swaps the connection to one we already solve on
+    // while keeping its auctions
+    pub async fn add_chain(&mut self, chain_id: u64, chain: Arc<CSP>) -> eyre::Result<()> {
+        if !self.states.contains_key(&chain_id) {
+            let state = chain.fetch_state().await?;
+            if let Some(demo_transfers) = self.demo_transfers.as_mut() {
+                demo_transfers.insert(chain_id, state.transfers.iter().map(|t| t.request_id).collect());
+            }
+            self.states.insert(chain_id, state);
+        }
+        self.chains.insert(chain_id, chain);
+        self.scheduler.invalidate();
+
+        // requests from the other chains may have been waiting for this one to be their destination
+        let chain_ids: Vec<u64> = self.chains.keys().copied().collect();
+        for chain_id in chain_ids {
+            self.start_auctions_for_new_transfers(chain_id);
+        }
+        self.publish_states();
+        Ok(())
+    }
+    // Stops solving on a chain, dropping the auctions for fills on it
+    pub fn remove_chain(&mut self, chain_id: u64) {
+        self.chains.remove(&chain_id);
+        self.states.remove(&chain_id);
+        if let Some(demo_transfers) = self.demo_transfers.as_mut() {
+            demo_transfers.remove(&chain_id);
+        }
+        self.scheduler.invalidate();
+        self.metrics.forget_chain(chain_id);
+        self.status.forget_chain(chain_id);
+    }
+    // The decay curve only applies to auctions started from now on
+    pub fn set_strategy(&mut self, threshold_multiplier: f64, solver_name: String, decay_curve: DecayCurve) {
+        if threshold_multiplier != self.threshold_multiplier || solver_name != self.solver_name || decay_curve != self.decay_curve {
+            info!(solver = %solver_name, threshold_multiplier, ?decay_curve, "updated solver strategy");
+        }
+        self.threshold_multiplier = threshold_multiplier;
+        self.solver_name = solver_name;
+        self.decay_curve = decay_curve;
+        self.scheduler.invalidate();
+    }
+    // Used to weigh fills against each other when they compete for inventory
+    pub fn set_fill_costs(&mut self, fill_costs: HashMap<u64, U256>) {
+        self.fill_costs = fill_costs;
+        self.scheduler.invalidate();
     }
-    pub async fn fetch_state(&mut self, chain_id: u64, in_flight: &Cache<RequestId, ()>) -> eyre::Result<Vec<Trade>> {
+    pub fn set_risk_limits(&mut self, config: RiskConfig) {
+        self.risk.lock().expect("risk limits lock poisoned").set_config(config);
+        self.scheduler.invalidate();
+    }
+    pub fn set_demo_mode(&mut self, demo_mode: bool) {
+        match (demo_mode, self.demo_transfers.is_some()) {
+            (true, false) => {
+                warn!("demo mode enabled, running auctions for transfers there already are, even once fulfilled");
+                self.demo_transfers = Some(self.states.iter().map(|(chain_id, state)| (*chain_id, state.transfers.iter().map(|t| t.request_id).collect())).collect());
+            }
+            (false, true) => self.demo_transfers = None,
+            _ => {}
+        }
+        self.scheduler.invalidate();
+    }
+    pub fn set_compliance(&mut self, compliance: Compliance) {
+        self.compliance = compliance;
+        self.scheduler.invalidate();
+    }
+    pub async fn fetch_state(&mut self, event: &BlockEvent, in_flight: &Cache<RequestId, ()>) -> eyre::Result<Vec<Trade>> {
+        let chain_id = event.chain_id;
         let chain = self.chains.get(&chain_id).expect("somehow got event for a non-existent chain");
         let mut updated_state = chain.fetch_state().await?;
+        // the block event drives the auction clock of this chain
+        updated_state.block_number = event.block_number;
+        updated_state.block_timestamp = event.timestamp;
 
         // Preserve existing auctions from the old state
         if let Some(existing_state) = self.states.get(&chain_id) {
             updated_state.active_auctions = existing_state.active_auctions.clone();
         }
 
+        // fills that have landed are reflected in the balance we just fetched
+        self.reservations.settle(chain_id, &updated_state.already_fulfilled);
+
+        // requests we filled that the source chain no longer owes us for have been paid out
+        let awaiting_refund: Vec<RequestId> = updated_state.transfers.iter().map(|t| t.request_id).collect();
+        self.status.refunds_received(chain_id, &awaiting_refund);
+        self.risk.lock().expect("risk limits lock poisoned").refunds_received(chain_id, &awaiting_refund);
+
         // Insert the updated state FIRST
         self.states.insert(chain_id, updated_state);
         
         // Start auctions for new transfers
         self.start_auctions_for_new_transfers(chain_id);
         
-        // Calculate trades for all known chains
-        let mut all_trades = Vec::new();
+        // Calculate the fills we could make for all known chains, from the transfers the block may have changed
+        let mut candidates = Vec::new();
+        let mut skips = SkipReport::new(chain_id, event.block_number);
+        self.randomness = None;
         
         // Check all configured chains
-        for &chain_id in self.chains.keys() {
-            let mut chain_trades = self.calculate_trades_internal(chain_id, in_flight).await;
-            all_trades.append(&mut chain_trades);
+        let chain_ids: Vec<u64> = self.chains.keys().copied().collect();
+        for src_chain_id in chain_ids {
+            let mut chain_candidates = self.calculate_trades_internal(src_chain_id, chain_id, in_flight, &mut skips).await;
+            candidates.append(&mut chain_candidates);
         }
-        
+
+        // fills competing for the same inventory are chosen between, rather than made first come first served
+        let all_trades = self.select_trades(candidates, &mut skips);
+        let live: HashSet<RequestId> = self.states.values().flat_map(|state| state.transfers.iter().map(|t| t.request_id)).collect();
+        self.scheduler.finish_round(&live);
+
+        self.publish_states();
+        self.metrics.observe_skips(&skips);
+        self.status.publish_skips(skips);
         Ok(all_trades)
     }
-    // Helper method to check if a transfer is new (appeared after startup)
-    fn is_new_transfer(&self, chain_id: u64, request_id: &RequestId) -> bool {
-        if let Some(initial_transfers) = self.initial_transfers.get(&chain_id) {
-            !initial_transfers.contains(request_id)
-        } else {
-            true // If we don't have initial state, consider it new
+    pub fn state(&self, chain_id: u64) -> Option<&ChainState> {
+        self.states.get(&chain_id)
+    }
+    pub fn reservations(&self) -> Arc<Reservations> {
+        self.reservations.clone()
+    }
+    pub fn risk(&self) -> Arc<Mutex<RiskLimits>> {
+        self.risk.clone()
+    }
+    // Publish the latest view of every chain to the metrics and the API
+    fn publish_states(&self) {
+        for (chain_id, state) in &self.states {
+            self.metrics.observe_chain(*chain_id, state);
+            self.status.publish_chain(*chain_id, state, |auction| Self::execution_threshold(self.threshold_multiplier, auction).1);
         }
     }
-
-    // Toggle demo mode to allow processing pre-fulfilled transfers
-    pub fn set_demo_mode(&mut self, enabled: bool) {
-        self.demo_mode = enabled;
-        println!("🎮 Demo mode {}", if enabled { "enabled" } else { "disabled" });
+    // How far down from the start price a solver with this multiplier waits, in percent, and the price that is
+    fn execution_threshold(threshold_multiplier: f64, auction: &DutchAuction) -> (u64, U256) {
+        // Fixed threshold calculation: Use auction start_fee with diminishing thresholds
+        // Higher threshold_multiplier = willing to pay closer to start price = more aggressive
+        let price_range = auction.start_fee.saturating_sub(auction.reserve_fee);
+
+        // CORRECTED: Lower multiplier = smaller percentage down = more aggressive
+        // AggressiveSolver (1.1): 10% down from start = executes early at high price
+        // ModerateSolver (1.5): 33% down from start  
+        // ConservativeSolver (2.0): 50% down from start = waits for lower prices
+        let percentage_down = if threshold_multiplier <= 1.2 {
+            10  // 1.1x - very aggressive (10% down)
+        } else if threshold_multiplier <= 1.7 {
+            33  // 1.5x - moderate (33% down)
+        } else {
+            50  // 2.0x - conservative (50% down)
+        };
+        (percentage_down, auction.start_fee.saturating_sub(apply_bps(price_range, percentage_down * 100)))
     }
-    
-    /// Refresh blockchain state for a specific chain to detect completed trades
-    pub async fn refresh_chain_state(&mut self, chain_id: u64) -> eyre::Result<()> {
-        // For now, just call fetch_state without returning trades to refresh internal state
-        let _ = self.fetch_state(chain_id, &moka::sync::Cache::new(1000)).await?;
-        Ok(())
+    // Whether we're in demo mode and the transfer was there when it was turned on
+    fn is_demo_transfer(&self, chain_id: u64, request_id: &RequestId) -> bool {
+        self.demo_transfers.as_ref().and_then(|demo_transfers| demo_transfers.get(&chain_id)).is_some_and(|request_ids| request_ids.contains(request_id))
     }
 
-    // Fixed method - creates auctions on destination chains, not source chains
+    // Fixed method - creates auctions on destination chains, not source chains, and re-prices them on fee updates
     fn start_auctions_for_new_transfers(&mut self, chain_id: u64) {
-        // Get the transfers from this chain (source chain)
-        let transfers = if let Some(state) = self.states.get(&chain_id) {
-            state.transfers.clone()
+        // Get the transfers from this chain (source chain), whose clock the auctions run on
+        let curve = self.decay_curve.clone();
+        let (transfers, clock) = if let Some(state) = self.states.get(&chain_id) {
+            (state.transfers.clone(), state.auction_clock)
         } else {
             return;
         };
         
         // For each transfer, create auction on the DESTINATION chain
         for transfer in &transfers {
+            let _span = request_span(transfer.request_id).entered();
             let dest_chain_id = normalise_chain_id(transfer.params.dstChainId);
-            
+
             // Create auction on destination chain, not source chain
             if let Some(dest_state) = self.states.get_mut(&dest_chain_id) {
-                // Check if this is a new transfer (auction doesn't exist yet)
-                if !dest_state.active_auctions.contains_key(&transfer.request_id) {
-                    // Use slippage-based auction: solverFee is now slippage tolerance
-                    let expected_blocks = 120; // Extended duration for better competition
-                    let auction = DutchAuction::new_slippage_based(
-                        transfer.params.amountOut, // Token amount
-                        transfer.params.solverFee, // Slippage tolerance (repurposed)
-                        expected_blocks
-                    );
-                    println!("🚀 Started slippage-based Dutch auction for request {:?} on DESTINATION chain {}", 
-                        transfer.request_id, dest_chain_id);
-                    println!("   Amount: {}, Slippage: {}", transfer.params.amountOut, transfer.params.solverFee);
-                    println!("   Start price: {}, Reserve price (minAllowedCost): {}", 
-                        auction.start_fee, auction.reserve_fee);
-                    
-                    dest_state.active_auctions.insert(transfer.request_id, auction);
+                match dest_state.active_auctions.entry(transfer.request_id) {
+                    // Check if this is a new transfer (auction doesn't exist yet)
+                    Entry::Vacant(entry) => {
+                        // Use slippage-based auction: solverFee is now slippage tolerance
+                        let duration = 120; // Extended duration for better competition, in seconds or blocks
+                        // anchor to when the request was made on-chain so that every solver (and every restart)
+                        // runs the same auction; until we know when that was, we try again on later blocks
+                        let Some(start) = clock.requested_at(transfer) else {
+                            debug!("couldn't determine when request was made yet, not starting its auction");
+                            continue;
+                        };
+                        let auction = DutchAuction::new_slippage_based(
+                            transfer.params.amountOut, // Token amount
+                            transfer.params.solverFee, // Slippage tolerance (repurposed)
+                            clock,
+                            curve.clone(),
+                            start,
+                            duration,
+                        );
+                        info!(
+                            src_chain_id = chain_id,
+                            dest_chain_id,
+                            amount = %transfer.params.amountOut,
+                            slippage = %transfer.params.solverFee,
+                            start_fee = %auction.start_fee,
+                            reserve_fee = %auction.reserve_fee,
+                            clock = ?auction.clock,
+                            start_time = auction.start_time,
+                            end_time = auction.end_time,
+                            "started slippage-based Dutch auction on destination chain"
+                        );
+
+                        self.status.publish(SolverEvent::RequestDiscovered {
+                            request_id: B256::from(transfer.request_id),
+                            src_chain_id: chain_id,
+                            dest_chain_id,
+                            amount_out: transfer.params.amountOut,
+                            solver_fee: transfer.params.solverFee,
+                        });
+                        self.status.publish(SolverEvent::AuctionStarted {
+                            request_id: B256::from(transfer.request_id),
+                            dest_chain_id,
+                            start_time: auction.start_time,
+                            end_time: auction.end_time,
+                            start_fee: auction.start_fee,
+                            reserve_fee: auction.reserve_fee,
+                        });
+                        entry.insert(auction);
+                    }
+                    Entry::Occupied(mut entry) if entry.get().slippage != transfer.params.solverFee => {
+                        let auction = entry.get_mut();
+                        // the user called `updateSolverFeesIfUnfulfilled` (emitting `SwapRequestSolverFeeUpdated`),
+                        // which we see as new params for the request; re-price rather than keep the stale fees
+                        let previous_slippage = auction.slippage;
+                        auction.reprice(transfer.params.solverFee);
+                        info!(
+                            from = %previous_slippage,
+                            to = %transfer.params.solverFee,
+                            start_fee = %auction.start_fee,
+                            reserve_fee = %auction.reserve_fee,
+                            "fee updated, re-priced auction"
+                        );
+                    }
+                    Entry::Occupied(_) => {}
                 }
             }
         }
     }
-    // New internal method that works with self.states directly
-    async fn calculate_trades_internal(&mut self, chain_id: u64, in_flight: &Cache<RequestId, ()>) -> Vec<Trade> {
+    // New internal method that works with self.states directly; returns the fills of transfers from
+    // the chain that we could make, each of which fits in its destination's inventory on its own.
+    // Transfers a block on `changed_chain_id` can't have changed keep their last outcome.
+    async fn calculate_trades_internal(&mut self, chain_id: u64, changed_chain_id: u64, in_flight: &Cache<RequestId, ()>, skips: &mut SkipReport) -> Vec<(Transfer, Trade)> {
         let mut trades = Vec::new();
-        
-        println!("Checking chain {} for trades", chain_id);
 
         // Get transfers without cloning states
         let transfers = self.states
@@ -143,32 +334,127 @@ impl<'a, CSP: ChainStateProvider> Solver<'a, CSP> {
             .transfers
             .clone(); // Only clone transfers
 
-        // Only log when there are transfers to process
-        if !transfers.is_empty() {
-                    // Reduced verbosity - only log checking when needed
-            println!("Checking chain {} for trades...", chain_id);
-            println!("Found {} transfers on chain {}", transfers.len(), chain_id);
-        }
+        debug!(chain_id, transfers = transfers.len(), "checking chain for trades");
 
         for transfer in &transfers {
-            if in_flight.contains_key(&transfer.request_id) {
-                println!("Skipping transfer {:?} - already in flight", transfer.request_id);
+            if let Some(reason) = self.scheduler.held(transfer, changed_chain_id) {
+                skips.record(transfer, reason);
                 continue;
             }
-            
-            // Call solve with direct access to self.states (no cloning!)
-            self.solve_internal(&transfer, &mut trades).await;
+
+            let dest_chain_id = normalise_chain_id(transfer.params.dstChainId);
+            let result = if in_flight.contains_key(&transfer.request_id) {
+                request_span(transfer.request_id).in_scope(|| debug!("skipping transfer, already in flight"));
+                Err(SkipReason::InFlight)
+            } else {
+                // Call solve with direct access to self.states (no cloning!)
+                self.solve_internal(transfer).instrument(request_span(transfer.request_id)).await
+            };
+
+            match result {
+                Ok(trade) => trades.push((transfer.clone(), trade)),
+                Err(reason) => {
+                    self.status.publish(SolverEvent::skip(&transfer.request_id, dest_chain_id, reason));
+                    skips.record(transfer, reason);
+                    self.scheduler.evaluated(transfer.request_id, Some(reason));
+                }
+            }
+        }
+
+        trades
+    }
+
+    // Picks, per destination chain, the fills that earn the most from the inventory available there while
+    // staying within the risk limits. Fills that would breach a limit are left out of the choice, and the rest
+    // are skipped for lack of tokens.
+    fn select_trades(&mut self, candidates: Vec<(Transfer, Trade)>, skips: &mut SkipReport) -> Vec<Trade> {
+        let mut by_dest: BTreeMap<u64, Vec<(Transfer, Trade)>> = BTreeMap::new();
+        for (transfer, trade) in candidates {
+            by_dest.entry(normalise_chain_id(trade.dest_chain_id)).or_default().push((transfer, trade));
+        }
+
+        let mut trades = Vec::new();
+        for (dest_chain_id, candidates) in by_dest {
+            let fill_cost = self.fill_costs.get(&dest_chain_id).copied().unwrap_or_default();
+            // fills that would breach a limit on their own don't compete at all
+            let mut pool = Vec::new();
+            for (transfer, trade) in candidates {
+                match self.check_risk(&trade) {
+                    Ok(()) => pool.push((transfer, trade)),
+                    Err(reason) => self.skip_candidate(dest_chain_id, &transfer, reason, skips),
+                }
+            }
+
+            // each fill counts towards the limits of the ones after it, so when one of the chosen would now breach
+            // a limit, it's dropped and the rest are chosen between again
+            loop {
+                let capacity = self.states.get(&dest_chain_id).map(|state| self.reservations.available(dest_chain_id, state)).unwrap_or_default();
+                let options: Vec<Candidate> = pool.iter().map(|(_, trade)| Candidate::new(trade.swap_amount, trade.auction_price, fill_cost)).collect();
+                let chosen = select(&options, capacity);
+
+                let mut breached = false;
+                let mut unchosen = Vec::new();
+                for (i, (transfer, trade)) in pool.into_iter().enumerate() {
+                    if breached || !chosen.contains(&i) {
+                        unchosen.push((transfer, trade));
+                    } else if let Err(reason) = self.check_risk(&trade) {
+                        self.skip_candidate(dest_chain_id, &transfer, reason, skips);
+                        breached = true;
+                    } else {
+                        let _span = request_span(trade.request_id).entered();
+                        self.scheduler.evaluated(trade.request_id, None);
+                        self.commit_trade(dest_chain_id, &trade);
+                        self.status.publish(SolverEvent::fill(&trade.request_id, dest_chain_id, trade.auction_price));
+                        trades.push(trade);
+                    }
+                }
+                pool = unchosen;
+                if !breached {
+                    break;
+                }
+            }
+
+            for (transfer, trade) in pool {
+                request_span(trade.request_id).in_scope(|| debug!(dest_chain_id, needed = %trade.swap_amount, "not selected for the inventory available"));
+                self.skip_candidate(dest_chain_id, &transfer, SkipReason::InsufficientTokens, skips);
+            }
         }
 
-        // Only log when trades are generated
-        if trades.len() > 0 {
-            println!("Generated {} trades from chain {}", trades.len(), chain_id);
+        if !trades.is_empty() {
+            info!(trades = trades.len(), "generated trades");
         }
         trades
     }
 
-    // New solve method that works with self.states directly
-    async fn solve_internal(&mut self, transfer_request: &Transfer, trades: &mut Vec<Trade>) {
+    // Whether a fill stays within the risk limits, given the fills made so far
+    fn check_risk(&mut self, trade: &Trade) -> Result<(), SkipReason> {
+        self.risk.lock().expect("risk limits lock poisoned").check(normalise_chain_id(trade.src_chain_id), trade.swap_amount).inspect_err(|reason| {
+            request_span(trade.request_id).in_scope(|| info!(solver = %self.solver_name, %reason, amount = %trade.swap_amount, "not executing trade, it would breach a risk limit"));
+        })
+    }
+
+    fn skip_candidate(&mut self, dest_chain_id: u64, transfer: &Transfer, reason: SkipReason, skips: &mut SkipReport) {
+        self.scheduler.evaluated(transfer.request_id, Some(reason));
+        self.status.publish(SolverEvent::skip(&transfer.request_id, dest_chain_id, reason));
+        skips.record(transfer, reason);
+    }
+
+    // Sets aside the tokens of a fill we're about to make and ends its auction
+    fn commit_trade(&mut self, dest_chain_id: u64, trade: &Trade) {
+        info!(solver = %self.solver_name, price = %trade.auction_price, "executing trade");
+        self.reservations.reserve(dest_chain_id, trade.token_addr, trade.request_id, trade.swap_amount);
+        self.risk.lock().expect("risk limits lock poisoned").record(trade.request_id, normalise_chain_id(trade.src_chain_id), trade.swap_amount);
+
+        // IMMEDIATE AUCTION CLEANUP: Mark transfer as fulfilled and remove auction
+        if let Some(dest_state) = self.states.get_mut(&dest_chain_id) {
+            dest_state.active_auctions.remove(&trade.request_id);
+            dest_state.already_fulfilled.push(trade.request_id);
+        }
+        debug!(solver = %self.solver_name, "auction terminated, trade executed");
+    }
+
+    // New solve method that works with self.states directly; returns the trade to make or why we won't
+    async fn solve_internal(&mut self, transfer_request: &Transfer) -> Result<Trade, SkipReason> {
         let SwapRequestParameters {
             dstChainId,
             tokenOut,
@@ -178,146 +464,164 @@ impl<'a, CSP: ChainStateProvider> Solver<'a, CSP> {
             ..
         } = transfer_request.params;
 
-        // println!(" Processing transfer {:?} for destination chain {}", transfer_request.request_id, normalise_chain_id(dstChainId));
+        // Check if this is a demo transfer before getting mutable borrow
+        let is_demo_transfer = self.is_demo_transfer(normalise_chain_id(transfer_request.params.srcChainId), &transfer_request.request_id);
 
-        // Check if this is a new transfer before getting mutable borrow
-        let is_new_transfer = self.is_new_transfer(normalise_chain_id(transfer_request.params.srcChainId), &transfer_request.request_id);
+        // Auctions run on the clock of the chain the request came from
+        let (src_block_number, src_block_timestamp, src_clock, src_own_addr) = self
+            .states
+            .get(&normalise_chain_id(transfer_request.params.srcChainId))
+            .map(|state| (state.block_number, state.block_timestamp, Some(state.auction_clock), Some(state.own_addr)))
+            .unwrap_or_default();
 
-        let dest_state = match self.states.get_mut(&normalise_chain_id(dstChainId)) {
+        let request_id = transfer_request.request_id;
+        let dest_chain_id = normalise_chain_id(dstChainId);
+        let dest_state = match self.states.get_mut(&dest_chain_id) {
             None => {
-                println!(" Destination chain {} not found in states", normalise_chain_id(dstChainId));
-                return;
-            }
-            Some(state) => {
-                println!(" Found destination chain {} with {} auctions", normalise_chain_id(dstChainId), state.active_auctions.len());
-                state
+                debug!(dest_chain_id, "destination chain not configured");
+                return Err(SkipReason::UnknownDestination);
             }
+            Some(state) => state,
         };
 
         if executed {
-            println!(" Transfer already executed, returning");
-            return;
+            debug!("transfer already executed");
+            return Err(SkipReason::AlreadyExecuted);
+        }
+
+        if let Some(reason) = transfer_request.rejected {
+            debug!(%reason, "source router's account of the request is inconsistent");
+            return Err(reason);
+        }
+
+        // the rebalancer's requests pay out to our own address on the destination, so filling one moves nothing
+        if src_own_addr == Some(transfer_request.params.sender) {
+            debug!("our own rebalancing request, leaving it to other solvers");
+            return Err(SkipReason::OwnRequest);
+        }
+
+        if let Err(reason) = self.compliance.check(transfer_request.params.sender, transfer_request.params.recipient) {
+            debug!(%reason, sender = %transfer_request.params.sender, recipient = %transfer_request.params.recipient, "not serving request");
+            return Err(reason);
         }
 
         if dest_state.already_fulfilled.contains(&transfer_request.request_id) {
-            println!(" Transfer already fulfilled on blockchain (new_transfer: {}, demo_mode: {})", is_new_transfer, self.demo_mode);
-            
-            if is_new_transfer && !self.demo_mode {
-                println!(" New transfer that was quickly fulfilled, skipping");
-                return;
-            } else if !is_new_transfer && self.demo_mode {
-                println!(" Pre-existing fulfilled transfer, allowing auction for demo purposes");
-            } else if !self.demo_mode {
-                println!(" Transfer already fulfilled, skipping (demo mode disabled)");
-                return;
+            if is_demo_transfer {
+                debug!("pre-existing fulfilled transfer, allowing auction for demo purposes");
+            } else {
+                debug!("transfer already fulfilled");
+                return Err(SkipReason::AlreadyFulfilled);
             }
         }
 
         if dest_state.native_balance == U256::from(0) {
-            println!(" No native balance, returning");
-            return;
+            debug!(dest_chain_id, "no native balance for gas");
+            return Err(SkipReason::NoNativeBalance);
         }
 
-        if dest_state.token_balance < amountOut {
-            println!(" Insufficient token balance: {} < {}, returning", dest_state.token_balance, amountOut);
-            return;
+        let available = self.reservations.available(dest_chain_id, dest_state);
+        if available < amountOut {
+            debug!(dest_chain_id, balance = %dest_state.token_balance, %available, needed = %amountOut, "insufficient token balance");
+            return Err(SkipReason::InsufficientTokens);
         }
 
         // Validate slippage tolerance (solverFee is now slippage in basis points)
         // Slippage should be between 0 and 10000 (0% to 100%)
-        if solverFee > U256::from(10000) {
-            println!(" Slippage tolerance too high: {} bps (max 10000), returning", solverFee);
-            return;
+        if solverFee > U256::from(FULL_BPS) {
+            debug!(slippage_bps = %solverFee, "slippage tolerance above 10000 bps");
+            return Err(SkipReason::SlippageTooHigh);
         }
 
         if tokenOut != dest_state.token_addr {
-            println!(" Token mismatch: {} != {}, returning", tokenOut, dest_state.token_addr);
-            return;
+            debug!(%tokenOut, expected = %dest_state.token_addr, "token mismatch");
+            return Err(SkipReason::TokenMismatch);
         }
 
-        // Check each validation condition with debug output
-        // println!("   Validating transfer conditions:");
-        // println!("   executed: {}", executed);
-        // println!("   already_fulfilled: {}", dest_state.already_fulfilled.contains(&transfer_request.request_id));
-        // println!("   native_balance: {}", dest_state.native_balance);
-        // println!("   token_balance: {} (needed: {})", dest_state.token_balance, amountOut);
-        // println!("   slippage_tolerance_bps: {}", solverFee);
-        // println!("   tokenOut matches: {}", tokenOut == dest_state.token_addr);
-
         // Slippage-based Dutch Auction Logic
         let (current_price, should_execute) = if let Some(auction) = dest_state.active_auctions.get_mut(&transfer_request.request_id) {
-            // println!(" Found slippage-based auction for {:?} on destination chain!", transfer_request.request_id);
-            
-            // Try to fetch randomness from drand, fallback to deterministic if it fails
-            let randomness = match crate::drand::DrandRandomness::new().get_normalized_random().await {
-                Ok(r) => {
-                    println!(" Using drand randomness: {:.6}", r);
+            // Try to fetch randomness from drand once a round, fallback to deterministic if it fails
+            let randomness = match self.randomness {
+                Some(r) => r,
+                None => {
+                    let r = match self.drand.get_random_bps().await {
+                        Ok(r) => {
+                            debug!(randomness_bps = r, "using drand randomness");
+                            r
+                        }
+                        Err(e) => {
+                            warn!(error = %e, "drand failed, using deterministic pricing");
+                            self.metrics.drand_fetch_failed();
+                            FULL_BPS / 2 // Fallback to 50% randomness (1x normal decay)
+                        }
+                    };
+                    self.randomness = Some(r);
                     r
                 }
-                Err(e) => {
-                    println!(" Drand failed ({}), using deterministic pricing", e);
-                    0.5 // Fallback to 50% randomness (1x normal decay)
-                }
             };
             
-            let current_price = auction.update_current_fee_with_randomness(randomness);
+            let now = auction.clock.now(src_block_number, src_block_timestamp);
+            let current_price = auction.update_current_fee_with_randomness(now, randomness);
             
-            // Fixed threshold calculation: Use auction start_fee with diminishing thresholds
-            // Higher threshold_multiplier = willing to pay closer to start price = more aggressive
             let start_fee = auction.start_fee;
             let min_allowed_cost = auction.reserve_fee;
-            let price_range = start_fee.saturating_sub(min_allowed_cost);
-            
-            // CORRECTED: Lower multiplier = smaller percentage down = more aggressive
-            // AggressiveSolver (1.1): 10% down from start = executes early at high price
-            // ModerateSolver (1.5): 33% down from start  
-            // ConservativeSolver (2.0): 50% down from start = waits for lower prices
-            let percentage_down = if self.threshold_multiplier <= 1.2 {
-                U256::from(10)  // 1.1x - very aggressive (10% down)
-            } else if self.threshold_multiplier <= 1.7 {
-                U256::from(33)  // 1.5x - moderate (33% down)
-            } else {
-                U256::from(50)  // 2.0x - conservative (50% down)
-            };
-            let execution_threshold = start_fee.saturating_sub((price_range * percentage_down) / U256::from(100));
+            let (percentage_down, execution_threshold) = Self::execution_threshold(self.threshold_multiplier, auction);
             let should_execute = current_price <= execution_threshold;
+            self.status.publish(SolverEvent::PriceTick {
+                request_id: B256::from(request_id),
+                dest_chain_id,
+                now,
+                current_fee: current_price,
+                execution_threshold,
+            });
             
-            println!(" Solver '{}' Auction {:?} - Current price: {}, StartFee: {}, MinAllowedCost: {}, Threshold ({}x = {}% down): {}, Execute: {}", 
-                self.solver_name, transfer_request.request_id, current_price, start_fee, min_allowed_cost,
-                self.threshold_multiplier, percentage_down, execution_threshold, should_execute);
-            
-            if auction.is_expired() {
-                println!("Auction {:?} expired, executing at minAllowedCost", transfer_request.request_id);
+            debug!(
+                solver = %self.solver_name,
+                current_price = %current_price,
+                start_fee = %start_fee,
+                min_allowed_cost = %min_allowed_cost,
+                threshold_multiplier = self.threshold_multiplier,
+                percentage_down,
+                execution_threshold = %execution_threshold,
+                should_execute,
+                "priced auction"
+            );
+
+            if auction.is_expired(now) {
+                info!("auction expired, executing at minAllowedCost");
                 (auction.reserve_fee, true)
             } else {
                 (current_price, should_execute)
             }
+        } else if src_clock.is_some_and(|clock| clock.requested_at(transfer_request).is_none()) {
+            // its auction starts once we know when it was requested, and not a moment before
+            debug!("auction not started yet, request time unknown");
+            return Err(SkipReason::UnknownRequestTime);
         } else {
-            println!(" No auction found for {:?} on destination chain {}", transfer_request.request_id, normalise_chain_id(dstChainId));
             // Fallback: treat solverFee as slippage and calculate minAllowedCost directly
-            let slippage_bps = U256::from(10000);
-            let min_allowed_cost = amountOut * (slippage_bps - solverFee) / slippage_bps;
-            println!("Using fallback slippage calculation - Amount: {}, Slippage: {}, MinAllowedCost: {}", 
-                amountOut, solverFee, min_allowed_cost);
+            let min_allowed_cost = apply_bps(amountOut, FULL_BPS - solverFee.to::<u64>());
+            info!(dest_chain_id, amount = %amountOut, slippage = %solverFee, min_allowed_cost = %min_allowed_cost, "no auction found, using fallback slippage calculation");
             (min_allowed_cost, true)
         };
 
         if !should_execute {
-            println!(" Solver '{}' not executing trade {:?} - price too high", self.solver_name, transfer_request.request_id);
-            return;
+            debug!(solver = %self.solver_name, "not executing trade, price too high");
+            return Err(SkipReason::PriceTooHigh);
         }
 
-        // 🚨 CRITICAL: Last-second check to prevent double execution
+        if let Err(reason) = self.controls.check(dest_chain_id, &request_id).and_then(|()| self.controls.check_circuit(normalise_chain_id(transfer_request.params.srcChainId), dest_chain_id)) {
+            info!(solver = %self.solver_name, %reason, "not executing trade");
+            return Err(reason);
+        }
+
+        // CRITICAL: Last-second check to prevent double execution
         // Double-check if trade was just executed by another solver
         if dest_state.already_fulfilled.contains(&transfer_request.request_id) {
-            println!(" Solver '{}' STOPPING execution - trade {:?} was just fulfilled by another solver!", self.solver_name, transfer_request.request_id);
-            return;
+            info!(solver = %self.solver_name, "stopping execution, trade was just fulfilled by another solver");
+            return Err(SkipReason::AlreadyFulfilled);
         }
 
-        println!("✅ Solver '{}' executing trade {:?} at price {}", self.solver_name, transfer_request.request_id, current_price);
-        
-        dest_state.token_balance -= amountOut;
-        
+        debug!(solver = %self.solver_name, price = %current_price, "trade can be executed");
+
         let trade = Trade {
             request_id: transfer_request.request_id,
             token_addr: transfer_request.params.tokenOut,
@@ -327,43 +631,12 @@ impl<'a, CSP: ChainStateProvider> Solver<'a, CSP> {
             swap_amount: amountOut,
             auction_price: current_price,
         };
-        
-        trades.push(trade);
-        
-        // ✅ IMMEDIATE AUCTION CLEANUP: Mark transfer as fulfilled and remove auction
-        dest_state.active_auctions.remove(&transfer_request.request_id);
-        dest_state.already_fulfilled.push(transfer_request.request_id);
-        
-        println!("🛑 Auction terminated for request {:?} - trade executed by {}", 
-            transfer_request.request_id, self.solver_name);
-    }
-
-    // Keep the old methods for backward compatibility with tests
-    fn start_new_auctions(&mut self, chain_id: u64) {
-        if let Some(state) = self.states.get_mut(&chain_id) {
-            let transfers = state.transfers.clone();
-            
-            for transfer in &transfers {
-                if !state.active_auctions.contains_key(&transfer.request_id) {
-                    // Keep old logic for backward compatibility in tests
-                    let auction = DutchAuction::new(
-                        transfer.params.solverFee,
-                        60,
-                        3.0
-                    );
-                    println!("🚀 Started Dutch auction for request {:?} on chain {}", 
-                        transfer.request_id, chain_id);
-                    println!("   Start price: {}, Reserve price: {}", 
-                        auction.start_fee, auction.reserve_fee);
-                    
-                    state.active_auctions.insert(transfer.request_id, auction);
-                }
-            }
-        }
+        Ok(trade)
     }
 }
 
 // Keep the old functions for tests (add active_auctions field to ChainState in tests)
+#[cfg(test)]
 fn calculate_trades(chain_id: u64, states: &HashMap<u64, ChainState>, in_flight: &Cache<RequestId, ()>) -> Vec<Trade> {
     let mut trades = Vec::new();
     let mut solve_states = states.clone();
@@ -378,13 +651,14 @@ fn calculate_trades(chain_id: u64, states: &HashMap<u64, ChainState>, in_flight:
         if in_flight.contains_key(&transfer.request_id) {
             continue;
         }
-        solve(&transfer, &mut trades, &mut solve_states);
+        solve(transfer, &mut trades, &mut solve_states);
     }
 
     trades
 }
 
 // Keep old solve function for tests
+#[cfg(test)]
 fn solve(transfer_request: &Transfer, trades: &mut Vec<Trade>, states: &mut HashMap<u64, ChainState>) {
     // ... existing solve logic without auction support for tests
     let SwapRequestParameters {
@@ -424,50 +698,61 @@ fn solve(transfer_request: &Transfer, trades: &mut Vec<Trade>, states: &mut Hash
 
 #[cfg(test)]
 mod tests {
+    use crate::admin::AdminControls;
+    use crate::config::RiskConfig;
     use crate::eth::IRouter::SwapRequestParameters;
-    use crate::model::{ChainState, Trade, Transfer};
+    use crate::events::SolverEvent;
+    use crate::metrics::Metrics;
+    use crate::model::{AuctionClock, BlockEvent, ChainState, DecayCurve, RequestId, SkipReason, Trade, Transfer};
     use crate::solver::{ChainStateProvider, Solver, calculate_trades};
+    use crate::status::SolverStatus;
     use crate::util::test::{generate_address, generate_request_id};
-    use alloy::primitives::{Address, U256, address};
+    use alloy::primitives::{Address, B256, U256, address};
     use async_trait::async_trait;
     use moka::sync::Cache;
     use speculoos::assert_that;
-    use speculoos::vec::VecAssertions;
+    use speculoos::prelude::*;
     use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::broadcast::Receiver;
 
     static USER_ADDR: Address = address!("0xdeadbeef6964af9d7eed9e03e53415d37aa96045");
     static TOKEN_ADDR: Address = address!("0xd8da6bf26964af9d7eed9e03e53415d37aa96045");
+    static OWN_ADDR: Address = address!("0x0000000000000000000000000000000000a11ce5");
+
+    #[tokio::test]
+    async fn decisions_are_published_as_events() {
+        // given
+        let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
+        let networks = two_chains(vec![transfer_params.clone()], 1000);
+
+        // when
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+        solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        // then
+        let request_id = transfer_params.request_id;
+        let published = published(&mut events);
+        assert_that!(published.iter().any(|e| matches!(e, SolverEvent::RequestDiscovered { .. }))).is_true();
+        assert_that!(published.iter().any(|e| matches!(e, SolverEvent::AuctionStarted { .. }))).is_true();
+        assert_that!(published).contains(SolverEvent::skip(&request_id, 2, SkipReason::PriceTooHigh));
+        assert_that!(published).contains(SolverEvent::fill(&request_id, 2, U256::from(50)));
+    }
 
     #[tokio::test]
     async fn transfers_created_through_solver_create_trades() {
         // given
-        let chain_id = 1;
         let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
-        let chain_one_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1),
-            token_balance: U256::from(1),
-            transfers: vec![transfer_params.clone()],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let chain_two_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(100),
-            token_balance: U256::from(1000),
-            transfers: Vec::default(),
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let chain_one = StubbedChain { state: chain_one_state };
-        let chain_two = StubbedChain { state: chain_two_state };
-        let networks = HashMap::from([(1, chain_one), (2, chain_two)]);
+        let networks = two_chains(vec![transfer_params.clone()], 1000);
 
         // when
-        let mut solver = Solver::from(&networks, 2.0, "TestSolver".to_string()).await.unwrap();
-        let trades = solver.fetch_state(chain_id, &Cache::new(1)).await.unwrap();
+        let (mut solver, _) = solver(&networks).await;
+        let opening_trades = solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
 
         // then
+        // the auction opens at 3x the minimum allowed cost and, once expired, executes at the minimum allowed cost
         let expected_output_amount = transfer_params.params.amountOut;
         let expected_trade = Trade {
             request_id: transfer_params.request_id,
@@ -476,37 +761,360 @@ mod tests {
             dest_chain_id: transfer_params.params.dstChainId,
             recipient_addr: transfer_params.params.recipient,
             swap_amount: expected_output_amount,
-            auction_price: U256::from(1), // Default solver fee from test
+            auction_price: U256::from(50), // 50% slippage on 100
         };
+        assert_that!(opening_trades).has_length(0);
         assert_that!(trades).has_length(1);
         assert_that!(trades[0]).is_equal_to(expected_trade);
     }
 
+    #[tokio::test]
+    async fn tokens_of_fills_not_yet_mined_are_not_spent_twice() {
+        // given
+        let (first, second) = (create_transfer_params(USER_ADDR, 1, 2, 600), create_transfer_params(USER_ADDR, 1, 2, 600));
+        let networks = two_chains(vec![first, second], 1000);
+        let (mut solver, _) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let filled = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+        // the fill hasn't been mined, so the chain still shows the whole balance
+        let while_pending = solver.fetch_state(&block_event(1, 131, 12358), &Cache::new(1)).await.unwrap();
+        {
+            let mut state = networks[&2].state.lock().unwrap();
+            state.token_balance = U256::from(400);
+            state.already_fulfilled = vec![filled[0].request_id];
+        }
+        solver.fetch_state(&block_event(2, 5, 12359), &Cache::new(1)).await.unwrap();
+        let reserved_once_mined = solver.reservations().reserved(2, TOKEN_ADDR);
+
+        // then
+        assert_that!(filled).has_length(1);
+        assert_that!(while_pending).has_length(0);
+        assert_that!(reserved_once_mined).is_equal_to(U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn fills_retried_after_an_rpc_error_hold_their_tokens_once() {
+        // given
+        let transfer = create_transfer_params(USER_ADDR, 1, 2, 100);
+        let networks = two_chains(vec![transfer.clone()], 1000);
+        let (mut solver, _) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        // sending the fill failed over RPC, so it may or may not land, and once it's no longer in flight and the
+        // destination still doesn't list it as fulfilled, we try again
+        let filled = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+        let retried = solver.fetch_state(&block_event(2, 5, 12358), &Cache::new(1)).await.unwrap();
+
+        // then
+        assert_that!(filled).has_length(1);
+        assert_that!(retried).has_length(1);
+        assert_that!(solver.reservations().reserved(2, TOKEN_ADDR)).is_equal_to(U256::from(100));
+        assert_that!(solver.risk().lock().unwrap().chain_exposure(1)).is_equal_to(U256::from(100));
+    }
+
+    #[tokio::test]
+    async fn competing_fills_are_chosen_by_profit_rather_than_order() {
+        // given
+        // a fill earns its amount less its auction price: 25 for each of the first two, and 54 for the last,
+        // whose auction comes down to 10% of its amount
+        let mut transfers = vec![create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 60)];
+        transfers[2].params.solverFee = U256::from(9000);
+        let networks = two_chains(transfers.clone(), 100);
+        let (mut solver, _) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        // then
+        let filled: Vec<_> = trades.iter().map(|trade| (trade.request_id, trade.auction_price)).collect();
+        assert_that!(filled).is_equal_to(vec![(transfers[2].request_id, U256::from(6))]);
+    }
+
+    #[tokio::test]
+    async fn our_own_rebalancing_requests_are_left_to_other_solvers() {
+        // given
+        // the rebalancer's request pays out to our own address on the destination chain
+        let transfer = create_transfer_params(OWN_ADDR, 1, 2, 100);
+        let networks = two_chains(vec![transfer.clone()], 1000);
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        // then
+        assert_that!(trades).is_empty();
+        assert_that!(published(&mut events)).contains(SolverEvent::skip(&transfer.request_id, 2, SkipReason::OwnRequest));
+    }
+
+    #[tokio::test]
+    async fn fills_that_would_breach_a_risk_limit_are_skipped() {
+        // given
+        let transfers = vec![create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 50)];
+        let networks = two_chains(transfers.clone(), 1000);
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.set_risk_limits(RiskConfig {
+            max_chain_exposure: Some(U256::from(60)),
+            ..Default::default()
+        });
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        // then
+        assert_that!(trades).has_length(1);
+        assert_that!(published(&mut events)).contains(SolverEvent::skip(&transfers[1].request_id, 2, SkipReason::ChainExposureLimit));
+    }
+
+    #[tokio::test]
+    async fn fills_past_a_risk_limit_dont_crowd_out_the_others() {
+        // given
+        // the last fill earns the most, but is larger than a single fill may be
+        let mut transfers = vec![create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 60)];
+        transfers[2].params.solverFee = U256::from(9000);
+        let networks = two_chains(transfers.clone(), 100);
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.set_risk_limits(RiskConfig {
+            max_trade_amount: Some(U256::from(55)),
+            ..Default::default()
+        });
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        // then
+        assert_that!(trades.iter().map(|trade| trade.request_id).collect::<Vec<_>>()).is_equal_to(vec![transfers[0].request_id, transfers[1].request_id]);
+        assert_that!(published(&mut events)).contains(SolverEvent::skip(&transfers[2].request_id, 2, SkipReason::TradeTooLarge));
+    }
+
+    #[tokio::test]
+    async fn requests_the_source_router_gets_wrong_are_refused() {
+        // given
+        let mut transfers = vec![create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 50)];
+        transfers[1].rejected = Some(SkipReason::RequestIdMismatch);
+        let networks = two_chains(transfers.clone(), 1000);
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        // then
+        assert_that!(trades.iter().map(|trade| trade.request_id).collect::<Vec<_>>()).is_equal_to(vec![transfers[0].request_id]);
+        assert_that!(published(&mut events)).contains(SolverEvent::skip(&transfers[1].request_id, 2, SkipReason::RequestIdMismatch));
+    }
+
+    #[tokio::test]
+    async fn fulfilled_transfers_are_never_traded_outside_demo_mode() {
+        // given
+        let (pre_existing, new) = (create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 50));
+
+        // when
+        let (production, production_events) = trade_fulfilled_transfers(false, &pre_existing, &new).await;
+        let (demo, demo_events) = trade_fulfilled_transfers(true, &pre_existing, &new).await;
+
+        // then
+        let priced = |events: &[SolverEvent], transfer: &Transfer| {
+            events.iter().any(|event| matches!(event, SolverEvent::PriceTick { request_id, .. } if *request_id == B256::from(transfer.request_id)))
+        };
+        assert_that!(production).is_empty();
+        assert_that!(production_events).contains(SolverEvent::skip(&pre_existing.request_id, 2, SkipReason::AlreadyFulfilled));
+        assert_that!(production_events).contains(SolverEvent::skip(&new.request_id, 2, SkipReason::AlreadyFulfilled));
+        assert_that!(priced(&production_events, &pre_existing)).is_false();
+        // the demo auction of the transfer that was there already is bid on for show, and the last check before
+        // trading still stops it
+        assert_that!(priced(&demo_events, &pre_existing)).is_true();
+        assert_that!(priced(&demo_events, &new)).is_false();
+        assert_that!(demo).is_empty();
+    }
+
+    // Starts a solver with `pre_existing` already fulfilled, then has `new` requested and fulfilled by someone else.
+    // Returns the requests we traded and what we published.
+    async fn trade_fulfilled_transfers(demo_mode: bool, pre_existing: &Transfer, new: &Transfer) -> (Vec<RequestId>, Vec<SolverEvent>) {
+        let networks = two_chains(vec![pre_existing.clone()], 1000);
+        networks[&2].state.lock().unwrap().already_fulfilled = vec![pre_existing.request_id];
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.set_demo_mode(demo_mode);
+
+        networks[&1].state.lock().unwrap().transfers.push(new.clone());
+        networks[&2].state.lock().unwrap().already_fulfilled.push(new.request_id);
+        solver.fetch_state(&block_event(2, 10, 12345), &Cache::new(1)).await.unwrap();
+        solver.fetch_state(&block_event(1, 10, 12345), &Cache::new(1)).await.unwrap();
+        let trades = solver.fetch_state(&block_event(1, 130, 12357), &Cache::new(1)).await.unwrap();
+
+        (trades.iter().map(|trade| trade.request_id).collect(), published(&mut events))
+    }
+
+    #[tokio::test]
+    async fn blocks_on_unrelated_chains_only_reprice_auctions() {
+        // given
+        // one transfer is refused for what only its chains can change, and the other waits for its price to come down
+        let (mut refused, priced) = (create_transfer_params(USER_ADDR, 1, 2, 50), create_transfer_params(USER_ADDR, 1, 2, 50));
+        refused.params.tokenOut = generate_address();
+        let mut networks = two_chains(vec![refused.clone(), priced.clone()], 1000);
+        networks.insert(3, Arc::new(StubbedChain::new(chain_state(vec![], 1000))));
+        let (mut solver, mut events) = solver(&networks).await;
+        solver.fetch_state(&block_event(1, 1, 12345), &Cache::new(1)).await.unwrap();
+
+        // when
+        let decided = |events: &mut Receiver<SolverEvent>| -> Vec<B256> {
+            published(events)
+                .iter()
+                .filter_map(|event| match event {
+                    SolverEvent::Decision { request_id, .. } => Some(*request_id),
+                    _ => None,
+                })
+                .collect()
+        };
+        let first = decided(&mut events);
+        solver.fetch_state(&block_event(3, 1, 12346), &Cache::new(1)).await.unwrap();
+        let unrelated = decided(&mut events);
+        solver.fetch_state(&block_event(2, 1, 12347), &Cache::new(1)).await.unwrap();
+        let destination = decided(&mut events);
+
+        // then
+        // the price of an auction moves with time and the randomness of the round, whichever chain the block is from
+        let (refused, priced) = (B256::from(refused.request_id), B256::from(priced.request_id));
+        assert_that!(first).is_equal_to(vec![refused, priced]);
+        assert_that!(unrelated).is_equal_to(vec![priced]);
+        assert_that!(destination).is_equal_to(vec![refused, priced]);
+    }
+
+    #[tokio::test]
+    async fn block_clock_auctions_expire_after_their_duration_in_blocks() {
+        // given
+        let mut transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
+        transfer_params.requested_block = Some(1000);
+        let networks = two_chains(vec![transfer_params], 1000);
+        let (mut solver, _) = solver(&networks).await;
+
+        // when
+        let at_start = solver.fetch_state(&block_event(1, 1000, 12345), &Cache::new(1)).await.unwrap();
+        // however random the decay, 10 blocks in the price can't have fallen past a 50% threshold
+        let early = solver.fetch_state(&block_event(1, 1010, 99999), &Cache::new(1)).await.unwrap();
+        let at_expiry = solver.fetch_state(&block_event(1, 1120, 99999), &Cache::new(1)).await.unwrap();
+
+        // then
+        assert_that!(at_start).has_length(0);
+        assert_that!(early).has_length(0);
+        assert_that!(at_expiry).has_length(1);
+        assert_that!(at_expiry[0].auction_price).is_equal_to(U256::from(50));
+    }
+
+    #[tokio::test]
+    async fn auctions_wait_for_the_block_their_request_was_made_in() {
+        // given
+        let mut transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
+        transfer_params.requested_block = None;
+        let networks = two_chains(vec![transfer_params.clone()], 1000);
+        let (mut solver, _) = solver(&networks).await;
+
+        // when
+        let unknown = solver.fetch_state(&block_event(1, 1005, 99999), &Cache::new(1)).await.unwrap();
+        let auction_while_unknown = solver.states[&2].active_auctions.get(&transfer_params.request_id).cloned();
+        networks[&1].state.lock().unwrap().transfers[0].requested_block = Some(1000);
+        let known = solver.fetch_state(&block_event(1, 1010, 99999), &Cache::new(1)).await.unwrap();
+        let auction_once_known = solver.states[&2].active_auctions[&transfer_params.request_id].clone();
+        let expired = solver.fetch_state(&block_event(1, 1120, 99999), &Cache::new(1)).await.unwrap();
+
+        // then
+        // rather than starting its auction at the block we first saw it in, we wait and start it where it was requested
+        assert_that!(unknown).has_length(0);
+        assert_that!(auction_while_unknown).is_none();
+        assert_that!(known).has_length(0);
+        assert_that!(auction_once_known.start_time).is_equal_to(1000);
+        assert_that!(expired).has_length(1);
+        assert_that!(expired[0].auction_price).is_equal_to(U256::from(50));
+    }
+
+    #[tokio::test]
+    async fn auctions_are_anchored_to_when_the_request_was_made() {
+        // given
+        // one request timed by timestamp and one by block number, both made 120 ticks before we first see them
+        let timestamp_transfer = create_transfer_params(USER_ADDR, 1, 3, 100);
+        let mut block_transfer = create_transfer_params(USER_ADDR, 2, 3, 100);
+        block_transfer.requested_block = Some(1000);
+        let networks = HashMap::from([
+            (1, Arc::new(StubbedChain::new(chain_state(vec![timestamp_transfer], 0)))),
+            (2, Arc::new(StubbedChain::new(ChainState { auction_clock: AuctionClock::BlockNumber, ..chain_state(vec![block_transfer], 0) }))),
+            (3, Arc::new(StubbedChain::new(chain_state(vec![], 1000)))),
+        ]);
+        let (mut solver, _) = solver(&networks).await;
+
+        // when
+        let timestamp_trades = solver.fetch_state(&block_event(1, 5, 12345 + 120), &Cache::new(1)).await.unwrap();
+        let block_trades = solver.fetch_state(&block_event(2, 1120, 99999), &Cache::new(1)).await.unwrap();
+
+        // then
+        // the auctions had already run their course, so they execute at the minimum allowed cost straight away
+        assert_that!(timestamp_trades).has_length(1);
+        assert_that!(timestamp_trades[0].auction_price).is_equal_to(U256::from(50));
+        assert_that!(block_trades).has_length(1);
+        assert_that!(block_trades[0].auction_price).is_equal_to(U256::from(50));
+    }
+
+    #[tokio::test]
+    async fn fee_updates_reprice_existing_auctions() {
+        // given
+        let mut transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
+        transfer_params.requested_block = Some(0);
+        let networks = two_chains(vec![transfer_params.clone()], 1000);
+        let (mut solver, _) = solver(&networks).await;
+        let original = solver.states[&2].active_auctions[&transfer_params.request_id].clone();
+
+        // when
+        // the user calls `updateSolverFeesIfUnfulfilled`, raising the fee from 50% to 80%
+        networks[&1].state.lock().unwrap().transfers[0].params.solverFee = U256::from(8000);
+        let trades = solver.fetch_state(&block_event(1, 1, 99999), &Cache::new(1)).await.unwrap();
+
+        // then
+        let repriced = &solver.states[&2].active_auctions[&transfer_params.request_id];
+        assert_that!(trades).has_length(0);
+        assert_that!(original.reserve_fee).is_equal_to(U256::from(50));
+        assert_that!(repriced.slippage).is_equal_to(U256::from(8000));
+        assert_that!(repriced.reserve_fee).is_equal_to(U256::from(20));
+        assert_that!(repriced.start_fee).is_equal_to(U256::from(60));
+        assert_that!(repriced.start_time).is_equal_to(original.start_time);
+        assert_that!(repriced.end_time).is_equal_to(original.end_time);
+    }
+
+    #[tokio::test]
+    async fn chains_can_be_added_replaced_and_removed_without_losing_auctions() {
+        // given
+        let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
+        let networks = HashMap::from([(1, Arc::new(StubbedChain::new(chain_state(vec![transfer_params.clone()], 1000))))]);
+        let (mut solver, _) = solver(&networks).await;
+
+        // when
+        // the destination chain is configured, then its RPC URL rotated, then chain 1 is removed
+        solver.add_chain(2, Arc::new(StubbedChain::new(chain_state(vec![], 1000)))).await.unwrap();
+        let added = solver.states[&2].active_auctions.contains_key(&transfer_params.request_id);
+        solver.add_chain(2, Arc::new(StubbedChain::new(chain_state(vec![], 1000)))).await.unwrap();
+        let replaced = solver.states[&2].active_auctions.contains_key(&transfer_params.request_id);
+        solver.remove_chain(1);
+
+        // then
+        assert_that!(added).is_true();
+        assert_that!(replaced).is_true();
+        assert_that!(solver.chains.contains_key(&1)).is_false();
+        assert_that!(solver.state(1)).is_none();
+        assert_that!(solver.status.chain(1)).is_none();
+        assert_that!(solver.status.chain(2)).is_some();
+    }
+
     #[test]
     fn multiple_transfers_create_multiple_trades() {
         // given
         // both transfers use 100
         let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
         let transfer_params_2 = create_transfer_params(USER_ADDR, 1, 2, 100);
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params, transfer_params_2],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        // on dst_chain, we only have enough balance to cover one tx
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(200),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params, transfer_params_2], 0)), (2, chain_state(vec![], 200))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -521,25 +1129,7 @@ mod tests {
         // both transfers use 100
         let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
         let transfer_params_2 = create_transfer_params(USER_ADDR, 1, 2, 100);
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(100),
-            transfers: vec![transfer_params],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        // on dst_chain, we only have enough balance to cover one tx
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(200),
-            transfers: vec![transfer_params_2],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params], 100)), (2, chain_state(vec![transfer_params_2], 200))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -551,23 +1141,7 @@ mod tests {
     #[test]
     fn no_transfers_creates_no_trades() {
         // given
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(1000),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![], 0)), (2, chain_state(vec![], 1000))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -579,23 +1153,11 @@ mod tests {
     #[test]
     fn no_native_currency_on_dest_chain_doesnt_trade() {
         // given
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![create_transfer_params(USER_ADDR, 1, 2, 100)],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
         let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(1000),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
+            native_balance: U256::ZERO,
+            ..chain_state(vec![], 1000)
         };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![create_transfer_params(USER_ADDR, 1, 2, 100)], 0)), (2, dst_chain_state)]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -607,23 +1169,7 @@ mod tests {
     #[test]
     fn no_token_balance_doesnt_trade() {
         // given
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![create_transfer_params(USER_ADDR, 1, 2, 100)],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(0),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![create_transfer_params(USER_ADDR, 1, 2, 100)], 0)), (2, chain_state(vec![], 0))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -637,24 +1183,7 @@ mod tests {
         // given
         let mut transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
         transfer_params.params.executed = true;
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(1000),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params], 0)), (2, chain_state(vec![], 1000))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -668,24 +1197,7 @@ mod tests {
         // given
         let mut transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
         transfer_params.params.solverFee = U256::from(0);
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(1000),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params], 0)), (2, chain_state(vec![], 1000))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -699,24 +1211,7 @@ mod tests {
         // given
         let mut transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
         transfer_params.params.tokenOut = generate_address();
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(1000),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params], 0)), (2, chain_state(vec![], 1000))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -731,25 +1226,8 @@ mod tests {
         // both transfers use 100
         let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
         let transfer_params_2 = create_transfer_params(USER_ADDR, 1, 2, 100);
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params, transfer_params_2],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
         // on dst_chain, we only have enough balance to cover one tx
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(150),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params, transfer_params_2], 0)), (2, chain_state(vec![], 150))]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -761,27 +1239,12 @@ mod tests {
     #[test]
     fn transfers_that_have_already_been_fulfilled_dont_make_trades() {
         // given
-        // both transfers use 100
         let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params.clone()],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        // on dst_chain, we only have enough balance to cover one tx
         let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(150),
-            transfers: vec![],
             already_fulfilled: vec![transfer_params.request_id],
-            active_auctions: HashMap::new(),
+            ..chain_state(vec![], 150)
         };
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        let state = HashMap::from([(1, chain_state(vec![transfer_params], 0)), (2, dst_chain_state)]);
 
         // when
         let trades = calculate_trades(1, &state, &Cache::new(1));
@@ -795,28 +1258,10 @@ mod tests {
         // given
         // transfer use 100
         let transfer_params = create_transfer_params(USER_ADDR, 1, 2, 100);
-
-        let src_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(0),
-            token_balance: U256::from(0),
-            transfers: vec![transfer_params.clone()],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
-        let dst_chain_state = ChainState {
-            token_addr: TOKEN_ADDR,
-            native_balance: U256::from(1000),
-            token_balance: U256::from(200),
-            transfers: vec![],
-            already_fulfilled: vec![],
-            active_auctions: HashMap::new(),
-        };
         // we create a cache that already has the request_id in it
         let cache = Cache::new(1);
-        let id = transfer_params.clone().request_id;
-        cache.insert(id, ());
-        let state = HashMap::from([(1, src_chain_state), (2, dst_chain_state)]);
+        cache.insert(transfer_params.request_id, ());
+        let state = HashMap::from([(1, chain_state(vec![transfer_params], 0)), (2, chain_state(vec![], 200))]);
 
         // when
         let trades = calculate_trades(1, &state, &cache);
@@ -842,16 +1287,73 @@ mod tests {
                 executed: false,
                 requestedAt: U256::from(12345),
             },
+            requested_block: Some(10),
+            rejected: None,
+        }
+    }
+
+    // A chain with `transfers` requested on it, holding `token_balance` of our token and native currency for gas
+    fn chain_state(transfers: Vec<Transfer>, token_balance: u64) -> ChainState {
+        ChainState {
+            token_addr: TOKEN_ADDR,
+            own_addr: OWN_ADDR,
+            native_balance: U256::from(100),
+            token_balance: U256::from(token_balance),
+            transfers,
+            already_fulfilled: vec![],
+            active_auctions: HashMap::new(),
+            block_number: 0,
+            block_timestamp: 0,
+            auction_clock: AuctionClock::Timestamp,
         }
     }
+
+    // Chain 1, timing its auctions in blocks, with `transfers` to chain 2, which holds `token_balance`
+    fn two_chains(transfers: Vec<Transfer>, token_balance: u64) -> HashMap<u64, Arc<StubbedChain>> {
+        HashMap::from([
+            (1, Arc::new(StubbedChain::new(ChainState { auction_clock: AuctionClock::BlockNumber, ..chain_state(transfers, 0) }))),
+            (2, Arc::new(StubbedChain::new(chain_state(vec![], token_balance)))),
+        ])
+    }
+
+    // A solver on `networks`, along with everything it publishes from the start
+    async fn solver(networks: &HashMap<u64, Arc<StubbedChain>>) -> (Solver<StubbedChain>, Receiver<SolverEvent>) {
+        let status = Arc::new(SolverStatus::default());
+        let events = status.subscribe();
+        let solver = Solver::from(networks, 2.0, "TestSolver".to_string(), DecayCurve::Linear, Arc::new(Metrics::new()), status, Arc::new(AdminControls::default())).await.unwrap();
+        (solver, events)
+    }
+
+    fn published(events: &mut Receiver<SolverEvent>) -> Vec<SolverEvent> {
+        let mut published = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            published.push(event);
+        }
+        published
+    }
+
+    fn block_event(chain_id: u64, block_number: u64, timestamp: u64) -> BlockEvent {
+        BlockEvent {
+            chain_id,
+            block_number,
+            timestamp,
+        }
+    }
+
     struct StubbedChain {
-        state: ChainState,
+        state: Mutex<ChainState>,
+    }
+
+    impl StubbedChain {
+        fn new(state: ChainState) -> Self {
+            Self { state: Mutex::new(state) }
+        }
     }
 
     #[async_trait]
     impl ChainStateProvider for StubbedChain {
         async fn fetch_state(&self) -> eyre::Result<ChainState> {
-            Ok(self.state.clone())
+            Ok(self.state.lock().unwrap().clone())
         }
     }
 }
@@ -0,0 +1,416 @@
+use crate::events::SolverEvent;
+use crate::model::{AuctionClock, ChainState, DecayCurve, DutchAuction, RequestId, Trade, Transfer};
+use crate::skips::SkipReport;
+use crate::util::{normalise_chain_id, request_span};
+use alloy::primitives::{Address, B256, TxHash, U256};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::info;
+
+// how many of the most recent trades we keep around for the API
+const MAX_TRADES: usize = 100;
+// how many events a slow subscriber can fall behind by before it misses some
+const EVENT_BUFFER: usize = 1024;
+
+// A read-only view of what the solver currently thinks, published by the `Solver` and
+// `TradeExecutor` and served by the `ApiServer`, along with a stream of the events leading to it
+pub(crate) struct SolverStatus {
+    chains: RwLock<BTreeMap<u64, ChainView>>,
+    trades: RwLock<VecDeque<TradeView>>,
+    skips: RwLock<SkipReport>, // from the latest round
+    events: broadcast::Sender<SolverEvent>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ChainSummary {
+    pub chain_id: u64,
+    pub token_addr: Address,
+    #[serde(serialize_with = "decimal")]
+    pub native_balance: U256,
+    #[serde(serialize_with = "decimal")]
+    pub token_balance: U256,
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub auction_clock: AuctionClock,
+    pub unfulfilled_requests: usize,
+    pub active_auctions: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct ChainView {
+    #[serde(flatten)]
+    pub summary: ChainSummary,
+    pub requests: Vec<RequestView>,
+    pub auctions: Vec<AuctionView>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct RequestView {
+    pub request_id: B256,
+    pub dest_chain_id: u64,
+    pub token_out: Address,
+    #[serde(serialize_with = "decimal")]
+    pub amount_out: U256,
+    #[serde(serialize_with = "decimal")]
+    pub solver_fee: U256,
+    pub requested_block: Option<u64>,
+    pub executed: bool,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct AuctionView {
+    pub request_id: B256,
+    pub dest_chain_id: u64,
+    pub clock: AuctionClock,
+    pub curve: DecayCurve,
+    pub start_time: u64,
+    pub end_time: u64,
+    #[serde(serialize_with = "decimal")]
+    pub amount: U256,
+    #[serde(serialize_with = "decimal")]
+    pub slippage: U256,
+    #[serde(serialize_with = "decimal")]
+    pub start_fee: U256,
+    #[serde(serialize_with = "decimal")]
+    pub reserve_fee: U256,
+    #[serde(serialize_with = "decimal")]
+    pub current_fee: U256,
+    #[serde(serialize_with = "decimal")]
+    pub execution_threshold: U256, // this solver executes once `current_fee` drops to or below this
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub(crate) enum TradeStatus {
+    Pending,
+    Succeeded { tx_hash: TxHash },
+    Failed { reason: String },
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub(crate) struct TradeView {
+    pub request_id: B256,
+    pub src_chain_id: u64,
+    pub dest_chain_id: u64,
+    pub token_addr: Address,
+    pub recipient_addr: Address,
+    #[serde(serialize_with = "decimal")]
+    pub swap_amount: U256,
+    #[serde(serialize_with = "decimal")]
+    pub auction_price: U256,
+    pub submitted_at: u64, // unix seconds
+    #[serde(flatten)]
+    pub status: TradeStatus,
+    pub refunded: bool, // whether the source chain has paid us out for it
+}
+
+impl SolverStatus {
+    // `threshold` gives the price at which this solver would execute the given auction
+    pub fn publish_chain(&self, chain_id: u64, state: &ChainState, threshold: impl Fn(&DutchAuction) -> U256) {
+        let mut auctions: Vec<AuctionView> = state
+            .active_auctions
+            .iter()
+            .map(|(request_id, auction)| AuctionView {
+                request_id: B256::from(*request_id),
+                dest_chain_id: chain_id,
+                clock: auction.clock,
+                curve: auction.curve.clone(),
+                start_time: auction.start_time,
+                end_time: auction.end_time,
+                amount: auction.amount,
+                slippage: auction.slippage,
+                start_fee: auction.start_fee,
+                reserve_fee: auction.reserve_fee,
+                current_fee: auction.current_fee,
+                execution_threshold: threshold(auction),
+            })
+            .collect();
+        auctions.sort_by_key(|auction| auction.request_id);
+
+        let view = ChainView {
+            summary: ChainSummary {
+                chain_id,
+                token_addr: state.token_addr,
+                native_balance: state.native_balance,
+                token_balance: state.token_balance,
+                block_number: state.block_number,
+                block_timestamp: state.block_timestamp,
+                auction_clock: state.auction_clock,
+                unfulfilled_requests: state.transfers.len(),
+                active_auctions: state.active_auctions.len(),
+            },
+            requests: state.transfers.iter().map(RequestView::from).collect(),
+            auctions,
+        };
+        self.chains.write().expect("status lock poisoned").insert(chain_id, view);
+    }
+
+    pub fn forget_chain(&self, chain_id: u64) {
+        self.chains.write().expect("status lock poisoned").remove(&chain_id);
+    }
+
+    pub fn chains(&self) -> Vec<ChainSummary> {
+        self.chains.read().expect("status lock poisoned").values().map(|chain| chain.summary.clone()).collect()
+    }
+
+    pub fn chain(&self, chain_id: u64) -> Option<ChainView> {
+        self.chains.read().expect("status lock poisoned").get(&chain_id).cloned()
+    }
+
+    pub fn auctions(&self) -> Vec<AuctionView> {
+        self.chains
+            .read()
+            .expect("status lock poisoned")
+            .values()
+            .flat_map(|chain| chain.auctions.clone())
+            .collect()
+    }
+
+    pub fn auction(&self, request_id: &RequestId) -> Option<AuctionView> {
+        let request_id = B256::from(*request_id);
+        self.chains
+            .read()
+            .expect("status lock poisoned")
+            .values()
+            .flat_map(|chain| chain.auctions.iter())
+            .find(|auction| auction.request_id == request_id)
+            .cloned()
+    }
+
+    // most recent first
+    pub fn trades(&self) -> Vec<TradeView> {
+        self.trades.read().expect("status lock poisoned").iter().rev().cloned().collect()
+    }
+
+    pub fn trade_submitted(&self, trade: &Trade) {
+        let view = TradeView {
+            request_id: B256::from(trade.request_id),
+            src_chain_id: normalise_chain_id(trade.src_chain_id),
+            dest_chain_id: normalise_chain_id(trade.dest_chain_id),
+            token_addr: trade.token_addr,
+            recipient_addr: trade.recipient_addr,
+            swap_amount: trade.swap_amount,
+            auction_price: trade.auction_price,
+            submitted_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            status: TradeStatus::Pending,
+            refunded: false,
+        };
+
+        let mut trades = self.trades.write().expect("status lock poisoned");
+        if trades.len() == MAX_TRADES {
+            trades.pop_front();
+        }
+        trades.push_back(view);
+    }
+
+    pub fn trade_finished(&self, request_id: &RequestId, status: TradeStatus) {
+        let request_id = B256::from(*request_id);
+        let mut trades = self.trades.write().expect("status lock poisoned");
+        if let Some(trade) = trades.iter_mut().rev().find(|trade| trade.request_id == request_id) {
+            trade.status = status;
+        }
+    }
+
+    // Marks our successful trades from `src_chain_id` that are no longer awaiting a refund as refunded
+    pub fn refunds_received(&self, src_chain_id: u64, awaiting_refund: &[RequestId]) {
+        let mut trades = self.trades.write().expect("status lock poisoned");
+        let refunded = trades.iter_mut().filter(|trade| {
+            trade.src_chain_id == src_chain_id
+                && !trade.refunded
+                && matches!(trade.status, TradeStatus::Succeeded { .. })
+                && !awaiting_refund.iter().any(|id| B256::from(*id) == trade.request_id)
+        });
+        for trade in refunded {
+            request_span(trade.request_id).in_scope(|| info!(src_chain_id, "refund received"));
+            trade.refunded = true;
+            self.publish(SolverEvent::RefundReceived {
+                request_id: trade.request_id,
+                src_chain_id,
+            });
+        }
+    }
+
+    pub fn publish_skips(&self, report: SkipReport) {
+        *self.skips.write().expect("status lock poisoned") = report;
+    }
+
+    pub fn skips(&self) -> SkipReport {
+        self.skips.read().expect("status lock poisoned").clone()
+    }
+
+    pub fn publish(&self, event: SolverEvent) {
+        // it's fine if nobody is listening
+        let _ = self.events.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SolverEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl Default for SolverStatus {
+    fn default() -> Self {
+        Self {
+            chains: RwLock::default(),
+            trades: RwLock::default(),
+            skips: RwLock::default(),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+}
+
+impl From<&Transfer> for RequestView {
+    fn from(transfer: &Transfer) -> Self {
+        Self {
+            request_id: B256::from(transfer.request_id),
+            dest_chain_id: normalise_chain_id(transfer.params.dstChainId),
+            token_out: transfer.params.tokenOut,
+            amount_out: transfer.params.amountOut,
+            solver_fee: transfer.params.solverFee,
+            requested_block: transfer.requested_block,
+            executed: transfer.params.executed,
+        }
+    }
+}
+
+// token amounts are easier to read (and to not lose precision on in JS) as decimal strings than as hex
+pub(crate) fn decimal<S: Serializer>(value: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::events::SolverEvent;
+    use crate::model::{AuctionClock, ChainState, DecayCurve, DutchAuction, Trade};
+    use crate::status::{SolverStatus, TradeStatus};
+    use alloy::primitives::{Address, B256, TxHash, U256};
+    use speculoos::assert_that;
+    use speculoos::prelude::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn published_auctions_can_be_looked_up_by_request_id() {
+        // given
+        let status = SolverStatus::default();
+        let auction = DutchAuction::new_slippage_based(U256::from(1000), U256::from(500), AuctionClock::Timestamp, DecayCurve::Linear, 0, 120);
+        let mut state = state();
+        state.active_auctions.insert([1; 32], auction);
+
+        // when
+        status.publish_chain(2, &state, |auction| auction.start_fee);
+
+        // then
+        let view = status.auction(&[1; 32]).unwrap();
+        assert_that!(view.dest_chain_id).is_equal_to(2);
+        assert_that!(view.execution_threshold).is_equal_to(U256::from(2850));
+        assert_that!(status.auction(&[2; 32])).is_none();
+        assert_that!(status.chains()).has_length(1);
+        assert_that!(status.chains()[0].active_auctions).is_equal_to(1);
+    }
+
+    #[test]
+    fn amounts_are_serialised_as_decimal_strings() {
+        // given
+        let status = SolverStatus::default();
+        let mut state = state();
+        state.token_balance = U256::from(1_000_000);
+
+        // when
+        status.publish_chain(1, &state, |auction| auction.start_fee);
+        let json = serde_json::to_value(status.chain(1).unwrap()).unwrap();
+
+        // then
+        assert_that!(json["token_balance"]).is_equal_to(serde_json::json!("1000000"));
+        assert_that!(json["chain_id"]).is_equal_to(serde_json::json!(1));
+    }
+
+    #[test]
+    fn trades_are_listed_most_recent_first_with_their_outcome() {
+        // given
+        let status = SolverStatus::default();
+        status.trade_submitted(&trade([1; 32]));
+        status.trade_submitted(&trade([2; 32]));
+
+        // when
+        status.trade_finished(&[1; 32], TradeStatus::Succeeded { tx_hash: TxHash::ZERO });
+        let trades = status.trades();
+
+        // then
+        assert_that!(trades).has_length(2);
+        assert_that!(trades[0].request_id).is_equal_to(B256::from([2; 32]));
+        assert_that!(trades[0].status).is_equal_to(TradeStatus::Pending);
+        assert_that!(trades[1].status).is_equal_to(TradeStatus::Succeeded { tx_hash: TxHash::ZERO });
+    }
+
+    #[test]
+    fn only_the_most_recent_trades_are_kept() {
+        // given
+        let status = SolverStatus::default();
+
+        // when
+        for i in 0..=100u8 {
+            status.trade_submitted(&trade([i; 32]));
+        }
+
+        // then
+        let trades = status.trades();
+        assert_that!(trades).has_length(100);
+        assert_that!(trades.last().unwrap().request_id).is_equal_to(B256::from([1; 32]));
+    }
+
+    #[test]
+    fn successful_trades_no_longer_awaiting_a_refund_are_reported_refunded_once() {
+        // given
+        let status = SolverStatus::default();
+        let mut events = status.subscribe();
+        status.trade_submitted(&trade([1; 32]));
+        status.trade_submitted(&trade([2; 32]));
+        status.trade_submitted(&trade([3; 32]));
+        status.trade_finished(&[1; 32], TradeStatus::Succeeded { tx_hash: TxHash::ZERO });
+        status.trade_finished(&[2; 32], TradeStatus::Succeeded { tx_hash: TxHash::ZERO });
+
+        // when
+        status.refunds_received(1, &[[2; 32]]);
+        status.refunds_received(1, &[]);
+
+        // then
+        let refund = |id: [u8; 32]| SolverEvent::RefundReceived {
+            request_id: B256::from(id),
+            src_chain_id: 1,
+        };
+        assert_that!(events.try_recv().unwrap()).is_equal_to(refund([1; 32]));
+        assert_that!(events.try_recv().unwrap()).is_equal_to(refund([2; 32]));
+        assert_that!(events.try_recv()).is_err();
+        assert_that!(status.trades().iter().filter(|trade| trade.refunded).count()).is_equal_to(2);
+    }
+
+    fn state() -> ChainState {
+        ChainState {
+            token_addr: Address::ZERO,
+            own_addr: Address::ZERO,
+            native_balance: U256::from(1),
+            token_balance: U256::ZERO,
+            transfers: vec![],
+            already_fulfilled: vec![],
+            active_auctions: HashMap::new(),
+            block_number: 0,
+            block_timestamp: 0,
+            auction_clock: AuctionClock::Timestamp,
+        }
+    }
+
+    fn trade(request_id: [u8; 32]) -> Trade {
+        Trade {
+            token_addr: Address::ZERO,
+            src_chain_id: U256::from(1),
+            dest_chain_id: U256::from(2),
+            recipient_addr: Address::ZERO,
+            request_id,
+            swap_amount: U256::from(100),
+            auction_price: U256::from(10),
+        }
+    }
+}
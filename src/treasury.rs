@@ -0,0 +1,104 @@
+use crate::config::TreasuryConfig;
+use crate::metrics::Metrics;
+use crate::network::Network;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use tracing::{info, warn};
+
+// Keeps the token balance of a chain's hot signer within bounds, so that a compromised hot key only
+// exposes what it needs for trading on that chain. Everything above the bounds is swept to the treasury,
+// and the treasury tops the signer up through an allowance it has granted it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Treasury {
+    pub address: Address,
+    pub min_token_balance: U256,
+    pub max_token_balance: U256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Rebalance {
+    Sweep(U256),
+    TopUp(U256),
+}
+
+impl Treasury {
+    pub fn from_config(config: &TreasuryConfig) -> eyre::Result<Self> {
+        Ok(Self {
+            address: config.address.parse()?,
+            min_token_balance: config.min_token_balance,
+            max_token_balance: config.max_token_balance,
+        })
+    }
+
+    pub fn rebalance_needed(&self, token_balance: U256) -> Option<Rebalance> {
+        if token_balance > self.max_token_balance {
+            Some(Rebalance::Sweep(token_balance - self.max_token_balance))
+        } else if token_balance < self.min_token_balance {
+            Some(Rebalance::TopUp(self.min_token_balance - token_balance))
+        } else {
+            None
+        }
+    }
+}
+
+// Moves tokens between the signer and the treasury of the network, if it has one, to bring `token_balance` back within bounds.
+// Returns what it moved, if anything, and the block the transfer landed in.
+pub(crate) async fn rebalance<P: Provider>(network: &Network<P>, token_balance: U256, metrics: &Metrics) -> eyre::Result<Option<(Rebalance, u64)>> {
+    let Some(treasury) = &network.treasury else {
+        return Ok(None);
+    };
+    let chain_id = network.chain_id;
+
+    match treasury.rebalance_needed(token_balance) {
+        None => Ok(None),
+        Some(Rebalance::Sweep(amount)) => {
+            let receipt = network.token.transfer(treasury.address, amount).send().await?.get_receipt().await?;
+            if !receipt.status() {
+                eyre::bail!("sweep to treasury reverted in {}", receipt.transaction_hash);
+            }
+            metrics.treasury_transfer(chain_id, "sweep", amount);
+            info!(chain_id, %amount, treasury = %treasury.address, tx_hash = %receipt.transaction_hash, "swept excess tokens to treasury");
+            Ok(Some((Rebalance::Sweep(amount), receipt.block_number.unwrap_or_default())))
+        }
+        Some(Rebalance::TopUp(needed)) => {
+            let allowance = network.token.allowance(treasury.address, network.own_addr).call().await?;
+            let available = network.token.balanceOf(treasury.address).call().await?;
+            let amount = needed.min(allowance).min(available);
+            if amount.is_zero() {
+                warn!(chain_id, %needed, %allowance, %available, treasury = %treasury.address, "token balance is low but the treasury can't top it up");
+                return Ok(None);
+            }
+
+            let receipt = network.token.transferFrom(treasury.address, network.own_addr, amount).send().await?.get_receipt().await?;
+            if !receipt.status() {
+                eyre::bail!("top-up from treasury reverted in {}", receipt.transaction_hash);
+            }
+            metrics.treasury_transfer(chain_id, "top_up", amount);
+            info!(chain_id, %amount, %needed, treasury = %treasury.address, tx_hash = %receipt.transaction_hash, "topped up tokens from treasury");
+            Ok(Some((Rebalance::TopUp(amount), receipt.block_number.unwrap_or_default())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::treasury::{Rebalance, Treasury};
+    use alloy::primitives::{Address, U256};
+    use speculoos::assert_that;
+
+    #[test]
+    fn balances_are_kept_within_bounds() {
+        // given
+        let treasury = Treasury {
+            address: Address::ZERO,
+            min_token_balance: U256::from(100),
+            max_token_balance: U256::from(500),
+        };
+
+        // then
+        assert_that!(treasury.rebalance_needed(U256::from(800))).is_equal_to(Some(Rebalance::Sweep(U256::from(300))));
+        assert_that!(treasury.rebalance_needed(U256::from(40))).is_equal_to(Some(Rebalance::TopUp(U256::from(60))));
+        assert_that!(treasury.rebalance_needed(U256::from(100))).is_equal_to(None);
+        assert_that!(treasury.rebalance_needed(U256::from(500))).is_equal_to(None);
+    }
+}
@@ -1,13 +1,21 @@
-use alloy::primitives::U256;
+use alloy::primitives::{B256, U256};
+use tracing::{Span, info_span};
 
 pub fn normalise_chain_id(chain_id: U256) -> u64 {
     chain_id.as_limbs()[0]
 }
 
+// Span covering everything we do for a single swap request (discovery, pricing, execution and
+// reconciliation), so that one request's history can be filtered out of the logs
+pub fn request_span(request_id: impl Into<B256>) -> Span {
+    info_span!("request", request_id = %request_id.into())
+}
+
 #[cfg(test)]
 pub(crate) mod test {
-    use crate::model::RequestId;
-    use alloy::primitives::Address;
+    use crate::config::NetworkConfig;
+    use crate::model::{AuctionClock, RequestId};
+    use alloy::primitives::{Address, U256};
     use std::fs::File;
     use std::io::Read;
 
@@ -26,4 +34,21 @@ pub(crate) mod test {
             .expect("failed to read random bytes");
         buf.into()
     }
+
+    // A network with nothing beyond what's required set; tests override what they care about with
+    // `NetworkConfig { .., ..network_config(chain_id) }`
+    pub fn network_config(chain_id: u64) -> NetworkConfig {
+        NetworkConfig {
+            chain_id,
+            rpc_url: "ws://localhost:8545".to_string(),
+            rusd_address: Address::ZERO.to_string(),
+            router_address: Address::ZERO.to_string(),
+            auction_clock: AuctionClock::Timestamp,
+            min_native_balance: U256::ZERO,
+            signer: None,
+            treasury: None,
+            inventory: None,
+            fill_cost: U256::ZERO,
+        }
+    }
 }